@@ -3,11 +3,9 @@ use async_trait::async_trait;
 use clap::{Arg, ArgAction, ArgMatches};
 use futures::StreamExt;
 use revm::primitives::alloy_primitives::TxHash;
+use rust_revm_simulations::commons::{subscribe, EthWsSubscriptionRequest, SimulationError};
 
-use crate::{
-    commands::Command,
-    commons::eth_ws_subscriber::{self, EthWsSubscriptionRequest},
-};
+use crate::commands::{parse_arg, Command};
 
 pub struct SubscribeNewBlockHeaders;
 
@@ -30,18 +28,15 @@ impl Command for SubscribeNewBlockHeaders {
         "subscribe-new-block-headers".to_owned()
     }
 
-    async fn execute(&self, args: &ArgMatches) {
-        let ws_url = args
-            .get_one::<String>("ws-url")
-            .expect("WS URL is required");
+    async fn execute(&self, args: &ArgMatches) -> Result<(), SimulationError> {
+        let ws_url = parse_arg::<String>(args, "ws-url")?;
         let subscription_request = EthWsSubscriptionRequest::new_heads(1);
-        let mut subscription =
-            eth_ws_subscriber::subscribe::<Block<TxHash>>(ws_url.to_owned(), subscription_request)
-                .await
-                .expect("Failed to subscribe");
+        let mut subscription = subscribe::<Block<TxHash>>(ws_url, subscription_request).await?;
 
         while let Some(block) = subscription.next().await {
-            println!("Received block: {:#?}", block.header);
+            println!("Received block: {:#?}", block?.header);
         }
+
+        Ok(())
     }
 }
@@ -1,52 +1,85 @@
-use alloy_eips::BlockNumberOrTag;
-use alloy_provider::Provider;
+use std::fmt;
+
+use alloy_eips::BlockId;
 use alloy_provider::ProviderBuilder;
 use alloy_rpc_types::Transaction;
-use anyhow::Result;
 use async_trait::async_trait;
 use clap::Arg;
 use clap::ArgAction;
 use clap::ArgMatches;
 use futures::StreamExt;
-use reqwest::Client;
+use reqwest::{Client, Url};
 use revm::db::{AlloyDB, CacheDB};
 use revm::primitives::alloy_primitives::TxHash;
-use revm::primitives::{TxKind, U256};
-use revm::Evm;
+use revm::primitives::{AccessList, Address, TxKind, U256};
+use revm::{DatabaseCommit, Evm};
+use rust_revm_simulations::commons::{
+    fetch_block, subscribe, EthWsSubscriptionRequest, Result, SimulationError, SimulationOutcome,
+};
+use serde::Serialize;
 
-use crate::commands::Command;
-use crate::commons::eth_ws_subscriber::{self, EthWsSubscriptionRequest};
+use crate::commands::{parse_arg, Command, OutputFormat};
 
 pub struct SubscribeNewPendingTransactions;
 
+/// Outcome of a pending transaction executed on top of the block, reverted or not, or why the
+/// EVM refused to execute it.
+#[derive(Debug, Serialize)]
+struct PendingTransactionReport {
+    hash: TxHash,
+    from: Address,
+    nonce: u64,
+    outcome: Option<SimulationOutcome<()>>,
+    error: Option<String>,
+}
+
+impl fmt::Display for PendingTransactionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transaction: {}", self.hash)?;
+        writeln!(f, "From: {}", self.from)?;
+        write!(f, "Nonce: {}", self.nonce)?;
+        if let Some(outcome) = &self.outcome {
+            write!(f, "\nStatus: {}", outcome.status)?;
+            write!(f, "\nGas used: {}", outcome.gas.gas_used)?;
+            write!(f, "\nLogs: {}", outcome.logs.len())?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, "\nError: {error}")?;
+        }
+
+        Ok(())
+    }
+}
+
 impl SubscribeNewPendingTransactions {
     async fn simulate_transactions(
         &self,
         transactions: Vec<Transaction>,
-        rpc_url: String,
+        rpc_url: Url,
+        block_id: BlockId,
+        output_format: OutputFormat,
     ) -> Result<()> {
-        let rpc_url = rpc_url.parse().unwrap();
         let client = ProviderBuilder::new().on_http(rpc_url);
 
-        let block = client
-            .get_block_by_number(BlockNumberOrTag::Latest, true)
-            .await
-            .unwrap()
-            .unwrap();
+        let block = fetch_block(&client, block_id).await?;
 
-        let mut database = CacheDB::new(AlloyDB::new(client, block.header.number.into()).unwrap());
+        let database = AlloyDB::new(client, block.header.number.into()).ok_or_else(|| {
+            SimulationError::Config("A multi-threaded tokio runtime is required to fork".to_owned())
+        })?;
+        let mut database = CacheDB::new(database);
 
         for transaction in transactions {
+            // Contract creations have no outcome of a call to report.
+            let Some(to) = transaction.to else {
+                continue;
+            };
+
             let mut evm = Evm::builder()
                 .with_db(&mut database)
                 .modify_tx_env(|tx| {
                     tx.caller = transaction.from;
-                    if let Some(to) = transaction.to {
-                        tx.transact_to = TxKind::Call(to);
-                    } else {
-                        tx.transact_to = TxKind::Create;
-                    }
-                    tx.data = transaction.input;
+                    tx.transact_to = TxKind::Call(to);
+                    tx.data = transaction.input.clone();
                     tx.value = transaction.value;
                     tx.gas_limit = transaction.gas;
                     tx.gas_price = U256::from(transaction.gas_price.unwrap_or_default());
@@ -59,33 +92,32 @@ impl SubscribeNewPendingTransactions {
                 })
                 .build();
 
-            println!(
-                "Executing transaction\nFrom: {:?}\nHash: {:?}\nNonce: {:?}",
-                transaction.from, transaction.hash, transaction.nonce
-            );
-
-            match evm.transact_commit() {
-                Ok(result) => match result {
-                    revm::primitives::ExecutionResult::Success { reason, .. } => println!(
-                        "Transaction {:?} executed successfully with result: {:?}",
-                        transaction.hash, reason
-                    ),
-                    revm::primitives::ExecutionResult::Revert { .. } => {
-                        println!("Transaction {:?} reverted", transaction.hash)
-                    }
-                    revm::primitives::ExecutionResult::Halt { reason, .. } => {
-                        println!(
-                            "Transaction {:?} halted with reason: {:?}",
-                            transaction.hash, reason
-                        )
-                    }
-                },
-                Err(e) => println!(
-                    "Error executing transaction {:?}: {:?}",
-                    transaction.hash, e
-                ),
+            // The state is committed even when the transaction fails, as it would be on chain,
+            // so the following transactions see the nonce and the gas paid.
+            let outcome = evm
+                .transact()
+                .map_err(SimulationError::from)
+                .and_then(|result| {
+                    let outcome = SimulationOutcome::from_execution(
+                        &result,
+                        to,
+                        transaction.input.clone(),
+                        AccessList::default(),
+                    );
+                    evm.db_mut().commit(result.state);
+                    outcome
+                });
+
+            let report = PendingTransactionReport {
+                hash: transaction.hash,
+                from: transaction.from,
+                nonce: transaction.nonce,
+                error: outcome.as_ref().err().map(ToString::to_string),
+                outcome: outcome.ok(),
             };
+            output_format.print(&report);
         }
+
         Ok(())
     }
 }
@@ -103,27 +135,36 @@ impl Command for SubscribeNewPendingTransactions {
                     .action(ArgAction::Set)
                     .help("The WS URL to subscribe to"),
             )
+            .arg(
+                Arg::new("block")
+                    .long("block")
+                    .help("The block on top of which transactions are simulated, either a number, a hash or a tag (latest, safe, finalized)")
+                    .default_value("latest")
+                    .action(ArgAction::Set),
+            )
     }
 
     fn name(&self) -> String {
         "subscribe-new-pending-transactions".to_owned()
     }
 
-    async fn execute(&self, args: &ArgMatches) {
-        let ws_url = args
-            .get_one::<String>("ws-url")
-            .expect("WS URL is required");
-        let rpc_url = ws_url.clone().replace("wss", "https");
+    async fn execute(&self, args: &ArgMatches) -> Result<()> {
+        let ws_url = parse_arg::<String>(args, "ws-url")?;
+        let rpc_url = ws_url.replace("wss", "https");
+        let rpc_url = Url::parse(&rpc_url).map_err(|e| {
+            SimulationError::Config(format!(
+                "Invalid RPC URL {rpc_url} derived from --ws-url: {e}"
+            ))
+        })?;
+        let block_id = parse_arg::<BlockId>(args, "block")?;
         let subscription_request = EthWsSubscriptionRequest::new_pending_transactions(1);
-        let mut subscription =
-            eth_ws_subscriber::subscribe::<TxHash>(ws_url.to_owned(), subscription_request)
-                .await
-                .expect("Failed to subscribe");
+        let mut subscription = subscribe::<TxHash>(ws_url, subscription_request).await?;
 
         let client = Client::new();
         let mut transactions = Vec::new();
 
         while let Some(transaction) = subscription.next().await {
+            let transaction = transaction?;
             let response = client
                 .post(rpc_url.clone())
                 .json(&serde_json::json!({
@@ -134,19 +175,20 @@ impl Command for SubscribeNewPendingTransactions {
                 }))
                 .send()
                 .await
-                .expect("Failed to send transaction to server");
+                .map_err(SimulationError::rpc)?;
 
             let response = response
                 .json::<serde_json::Value>()
                 .await
-                .expect("Failed to parse response");
+                .map_err(SimulationError::rpc)?;
 
-            let json_response = response.as_object().unwrap();
-            if json_response.contains_key("result") {
-                let result =
-                    serde_json::from_value::<Transaction>(json_response["result"].clone()).unwrap();
-
-                transactions.push(result.clone());
+            // Transactions that are no longer pending (or not yet propagated to the node) come
+            // back with a `null` result and are skipped.
+            let Some(result) = response.get("result").filter(|result| !result.is_null()) else {
+                continue;
+            };
+            if let Ok(result) = serde_json::from_value::<Transaction>(result.clone()) {
+                transactions.push(result);
 
                 if transactions.len() == 10 {
                     break;
@@ -154,8 +196,12 @@ impl Command for SubscribeNewPendingTransactions {
             }
         }
 
-        self.simulate_transactions(transactions, rpc_url)
-            .await
-            .unwrap();
+        self.simulate_transactions(
+            transactions,
+            rpc_url,
+            block_id,
+            OutputFormat::from_args(args),
+        )
+        .await
     }
 }
@@ -1,7 +1,7 @@
-use alloy_signer_local::PrivateKeySigner;
 use async_trait::async_trait;
 use clap::{Arg, ArgAction, ArgMatches};
-use revm::primitives::{keccak256, Address};
+
+use rust_revm_simulations::commons::{Identity, SimulationError};
 
 use crate::commands::Command;
 
@@ -28,26 +28,18 @@ impl Command for ComputeAddress {
         "compute-address-from-private-key".to_owned()
     }
 
-    async fn execute(&self, args: &ArgMatches) {
-        let private_keys = args
-            .get_many::<String>("private-keys")
-            .expect("At least one private key is required");
+    async fn execute(&self, args: &ArgMatches) -> Result<(), SimulationError> {
+        let private_keys = args.get_many::<String>("private-keys").ok_or_else(|| {
+            SimulationError::Config("At least one private key is required".to_owned())
+        })?;
 
         for private_key in private_keys {
-            let signer = &private_key.parse::<PrivateKeySigner>().unwrap();
-            let public_key = &signer
-                .clone()
-                .into_credential()
-                .verifying_key()
-                .to_encoded_point(false);
-
-            let public_key_bytes = &public_key.as_bytes()[1..];
-
-            let public_key_hash = keccak256(public_key_bytes);
-            let address = &public_key_hash[12..];
-            let address = Address::from_slice(address);
+            let identity = Identity::from_private_key(private_key)?;
+            let address = identity.address();
 
             println!("Address: {:#?}", address);
         }
+
+        Ok(())
     }
 }
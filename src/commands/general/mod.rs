@@ -1 +1,2 @@
 pub mod compute_address;
+pub mod token_safety;
@@ -0,0 +1,136 @@
+use alloy_eips::BlockId;
+use alloy_provider::ProviderBuilder;
+use async_trait::async_trait;
+use clap::{Arg, ArgAction, ArgMatches};
+use reqwest::Url;
+use revm::primitives::Address;
+use rust_revm_simulations::commons::{
+    fetch_block, ChainConfig, SimulationDatabase, SimulationError,
+};
+use rust_revm_simulations::token_safety::{
+    check_token, get_base_token, verify_trading_pool, TokenSafetyConfig, TradingPool, DEFAULT_BUYER,
+};
+use rust_revm_simulations::SimulationSession;
+
+use crate::commands::{
+    allow_unknown_pool_arg, check_pool, load_registry, parse_amount_arg, parse_arg, select_chain,
+    Command, OutputFormat,
+};
+
+fn config_from_args<DB: SimulationDatabase>(
+    args: &ArgMatches,
+    chain: &ChainConfig,
+    session: &mut SimulationSession<DB>,
+) -> Result<TokenSafetyConfig, SimulationError> {
+    let token = parse_arg::<Address>(args, "token")?;
+    let pool = parse_arg::<Address>(args, "pool")?;
+
+    let pool = match args.get_one::<String>("protocol").map(String::as_str) {
+        Some("v3") => TradingPool::UniswapV3 {
+            pool,
+            simulator: chain.uniswap_v3_simulator,
+        },
+        _ => TradingPool::UniswapV2(pool),
+    };
+    // The amount is spent in the other token of the pool, whose decimals apply.
+    let base_token = get_base_token(session, token, pool)?;
+    let amount = parse_amount_arg(args, "amount", base_token, session)?;
+
+    let mut config = TokenSafetyConfig::new(token, pool, amount);
+    if args.contains_id("recipient") {
+        config = config.with_recipient(parse_arg::<Address>(args, "recipient")?);
+    }
+
+    Ok(config)
+}
+
+pub struct TokenSafety;
+
+#[async_trait]
+impl Command for TokenSafety {
+    fn create(&self) -> clap::Command {
+        clap::Command::new("token-safety")
+            .about("Check whether a token can be bought, sold and transferred, and which taxes and limits it applies")
+            .long_flag("token-safety")
+            .arg(
+                Arg::new("rpc-url")
+                    .long("rpc-url")
+                    .action(ArgAction::Set)
+                    .required(true)
+                    .help("The RPC URL to connect to"),
+            )
+            .arg(
+                Arg::new("block")
+                    .long("block")
+                    .help("The block to fork from, either a number, a hash or a tag (latest, safe, finalized)")
+                    .default_value("latest")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("token")
+                    .long("token")
+                    .help("The token to check")
+                    .required(true)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("pool")
+                    .long("pool")
+                    .help("The pool trading the token")
+                    .required(true)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("protocol")
+                    .long("protocol")
+                    .help("The protocol of the pool")
+                    .value_parser(["v2", "v3"])
+                    .default_value("v2")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("amount")
+                    .long("amount")
+                    .help("The amount of the other token of the pool spent buying the token, either raw or in token units when it has a decimal point")
+                    .required(true)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("recipient")
+                    .long("recipient")
+                    .help("The account receiving the tokens in the transfer check")
+                    .action(ArgAction::Set),
+            )
+            .arg(allow_unknown_pool_arg())
+    }
+
+    fn name(&self) -> String {
+        "token-safety".to_owned()
+    }
+
+    async fn execute(&self, args: &ArgMatches) -> Result<(), SimulationError> {
+        let rpc_url = parse_arg::<Url>(args, "rpc-url")?;
+        let block_id = parse_arg::<BlockId>(args, "block")?;
+        let registry = load_registry(args)?;
+
+        let client = ProviderBuilder::new().on_http(rpc_url);
+        let block = fetch_block(&client, block_id).await?;
+
+        // The checks run from an account holding nothing, funded when buying.
+        let mut session = SimulationSession::fork(&client, &block, DEFAULT_BUYER).await?;
+        let chain = select_chain(args, &registry, session.cfg_env().chain_id)?;
+        let mut config = config_from_args(args, &chain, &mut session)?;
+        // The pool takes the fee of the factory that deployed it, Uniswap's when it is unknown.
+        if let Some(factory) =
+            check_pool(args, verify_trading_pool(&mut session, config.pool, &chain))?
+        {
+            config = config.with_fee(factory.fee);
+        }
+
+        let report = check_token(&mut session, &config)?;
+
+        OutputFormat::from_args(args).print(&report);
+
+        Ok(())
+    }
+}
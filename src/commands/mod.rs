@@ -1,11 +1,25 @@
+use alloy_rpc_types::Header;
+use alloy_signer_local::PrivateKeySigner;
 use async_trait::async_trait;
-use clap::ArgMatches;
+use clap::{Arg, ArgAction, ArgMatches};
 use eth_subscriptions::{
     subscribe_new_block_headers::SubscribeNewBlockHeaders,
     subscribe_new_pending_transactions::SubscribeNewPendingTransactions,
 };
-use general::compute_address::ComputeAddress;
+use general::{compute_address::ComputeAddress, token_safety::TokenSafety};
+use reqwest::Url;
+use revm::primitives::{Address, U256};
+use rust_revm_simulations::commons::{
+    submit_bundle, subscribe, BuilderClient, BundleSubmission, ChainConfig,
+    EthWsSubscriptionRequest, Identity, Registry, SignedTransaction, SimulationDatabase,
+    SimulationError, Subscription, TransactionSettings,
+};
+use rust_revm_simulations::{Erc20, SimulationSession};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
 use uniswap_v2::{
     swap_via_pool::SwapViaPool as UniswapV2SwapViaPool,
     swap_via_router::SwapViaRouter as UniswapV2SwapViaRouter,
@@ -19,13 +33,369 @@ pub mod uniswap_v3;
 
 #[async_trait]
 pub trait Command {
-    async fn execute(&self, args: &ArgMatches);
+    async fn execute(&self, args: &ArgMatches) -> Result<(), SimulationError>;
 
     fn create(&self) -> clap::Command;
 
     fn name(&self) -> String;
 }
 
+/// Parses the value of the argument `name`, failing with a configuration error when it is missing
+/// or invalid.
+pub fn parse_arg<T>(args: &ArgMatches, name: &str) -> Result<T, SimulationError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = args
+        .get_one::<String>(name)
+        .ok_or_else(|| SimulationError::Config(format!("--{name} is required")))?;
+
+    value
+        .parse()
+        .map_err(|e| SimulationError::Config(format!("Invalid --{name} {value}: {e}")))
+}
+
+/// Parses the amount of `token` given with `--{name}`, either raw (`1500000`) or in token units
+/// (`1.5`), which are scaled by the decimals of the token.
+pub fn parse_amount_arg<DB: SimulationDatabase>(
+    args: &ArgMatches,
+    name: &str,
+    token: Address,
+    session: &mut SimulationSession<DB>,
+) -> Result<U256, SimulationError> {
+    let value = args
+        .get_one::<String>(name)
+        .ok_or_else(|| SimulationError::Config(format!("--{name} is required")))?;
+
+    Erc20::new(token).parse_amount(value, session)
+}
+
+/// Argument letting the simulations run against a `--pool` that is not genuine.
+pub fn allow_unknown_pool_arg() -> Arg {
+    Arg::new("allow-unknown-pool")
+        .long("allow-unknown-pool")
+        .help("Only warn when --pool was not deployed by a known factory, instead of refusing it")
+        .action(ArgAction::SetTrue)
+}
+
+/// Fails when the pool is not genuine, unless `--allow-unknown-pool` is given, in which case it is
+/// only printed as a warning and `None` is returned.
+pub fn check_pool<T>(
+    args: &ArgMatches,
+    verification: Result<T, SimulationError>,
+) -> Result<Option<T>, SimulationError> {
+    match verification {
+        Ok(value) => Ok(Some(value)),
+        Err(SimulationError::Config(message)) if args.get_flag("allow-unknown-pool") => {
+            eprintln!("WARNING: {message}. Proceeding as --allow-unknown-pool is given");
+            Ok(None)
+        }
+        Err(SimulationError::Config(message)) => Err(SimulationError::Config(format!(
+            "{message}. Use --allow-unknown-pool to swap with it anyway"
+        ))),
+        Err(error) => Err(error),
+    }
+}
+
+/// Loads the registry from the file given with `--config`, or the built-in defaults otherwise.
+pub fn load_registry(args: &ArgMatches) -> Result<Registry, SimulationError> {
+    Registry::load(args.get_one::<String>("config").map(Path::new))
+}
+
+/// Selects the chain given with `--chain`, which must be the chain of the node, or the chain of
+/// the node when none is given.
+pub fn select_chain(
+    args: &ArgMatches,
+    registry: &Registry,
+    chain_id: u64,
+) -> Result<ChainConfig, SimulationError> {
+    let chain = match args.get_one::<String>("chain") {
+        Some(chain) => registry.find_chain(chain)?,
+        None => registry.chain(chain_id)?,
+    };
+
+    if chain.chain_id != chain_id {
+        return Err(SimulationError::Config(format!(
+            "--chain {} does not match the chain of the node ({chain_id})",
+            chain.name
+        )));
+    }
+
+    Ok(chain.clone())
+}
+
+/// Arguments selecting the account the transactions are sent from.
+pub fn identity_args() -> [Arg; 3] {
+    [
+        Arg::new("private-key")
+            .long("private-key")
+            .help("The private key of the account sending the transactions")
+            .conflicts_with_all(["keystore", "from"])
+            .action(ArgAction::Set),
+        Arg::new("keystore")
+            .long("keystore")
+            .help("A JSON keystore with the key of the account sending the transactions. The password is read from ETH_KEYSTORE_PASSWORD or prompted")
+            .conflicts_with("from")
+            .action(ArgAction::Set),
+        Arg::new("from")
+            .long("from")
+            .help("The account to simulate the transactions from, without being able to sign them")
+            .action(ArgAction::Set),
+    ]
+}
+
+/// Builds the identity given with `--private-key`, `--keystore` or `--from`, if any.
+pub fn identity_from_args(args: &ArgMatches) -> Result<Option<Identity>, SimulationError> {
+    if let Some(private_key) = args.get_one::<String>("private-key") {
+        return Identity::from_private_key(private_key).map(Some);
+    }
+
+    if let Some(keystore) = args.get_one::<String>("keystore") {
+        let password = match std::env::var("ETH_KEYSTORE_PASSWORD") {
+            Ok(password) => password,
+            Err(_) => rpassword::prompt_password(format!("Password for {keystore}: "))
+                .map_err(|e| SimulationError::Config(format!("Cannot read the password: {e}")))?,
+        };
+
+        return Identity::from_keystore(Path::new(keystore), &password).map(Some);
+    }
+
+    if args.contains_id("from") {
+        return Ok(Some(Identity::Address(parse_arg::<Address>(args, "from")?)));
+    }
+
+    Ok(None)
+}
+
+/// Arguments tuning the transactions built once the simulation succeeds.
+pub fn transaction_args() -> [Arg; 7] {
+    [
+        Arg::new("priority-fee")
+            .long("priority-fee")
+            .help("The priority fee per gas paid to the builder, in wei (1 gwei by default)")
+            .action(ArgAction::Set),
+        Arg::new("submit")
+            .long("submit")
+            .help("Send the signed transactions as a bundle to the builders")
+            .action(ArgAction::SetTrue),
+        Arg::new("builder")
+            .long("builder")
+            .help("A builder endpoint to send the bundle to, instead of the ones of the registry. Can be repeated")
+            .action(ArgAction::Append),
+        Arg::new("target-blocks")
+            .long("target-blocks")
+            .help("The number of blocks the bundle is sent for, starting at the next one")
+            .default_value("3")
+            .action(ArgAction::Set),
+        Arg::new("bundle-signing-key")
+            .long("bundle-signing-key")
+            .help("The private key identifying us to the builders. Defaults to the key signing the transactions")
+            .action(ArgAction::Set),
+        Arg::new("ws-url")
+            .long("ws-url")
+            .help("The WS URL used to watch the new blocks until the submitted transactions are included")
+            .requires("submit")
+            .action(ArgAction::Set),
+        Arg::new("monitor-blocks")
+            .long("monitor-blocks")
+            .help("The number of blocks to watch before giving up on the inclusion of the transactions")
+            .default_value("5")
+            .action(ArgAction::Set),
+    ]
+}
+
+pub fn transaction_settings_from_args(
+    args: &ArgMatches,
+) -> Result<TransactionSettings, SimulationError> {
+    let mut settings = TransactionSettings::default();
+    if args.contains_id("priority-fee") {
+        settings.max_priority_fee_per_gas = parse_arg::<u128>(args, "priority-fee")?;
+    }
+
+    Ok(settings)
+}
+
+/// How the signed transactions are submitted to the builders.
+pub struct BundleOptions {
+    builders: Vec<Url>,
+    target_blocks: u64,
+    auth_signer: PrivateKeySigner,
+}
+
+impl BundleOptions {
+    /// Parses the submission options, `None` when `--submit` is not given. It is checked upfront
+    /// that the transactions can be signed and that the bundle targets blocks yet to come.
+    pub fn from_args(
+        args: &ArgMatches,
+        identity: Option<&Identity>,
+    ) -> Result<Option<Self>, SimulationError> {
+        if !args.get_flag("submit") {
+            return Ok(None);
+        }
+
+        let Some(signer) = identity.and_then(Identity::signer) else {
+            return Err(SimulationError::Config(
+                "--submit requires --private-key or --keystore to sign the transactions".to_owned(),
+            ));
+        };
+        if args
+            .get_one::<String>("block")
+            .is_some_and(|block| block != "latest")
+        {
+            return Err(SimulationError::Config(
+                "--submit requires forking from the latest block".to_owned(),
+            ));
+        }
+
+        let auth_signer = match args.get_one::<String>("bundle-signing-key") {
+            Some(key) => Identity::from_private_key(key)?
+                .into_signer()
+                .ok_or_else(|| {
+                    SimulationError::Config(
+                        "--bundle-signing-key does not give a key to sign the bundles".to_owned(),
+                    )
+                })?,
+            None => signer.clone(),
+        };
+
+        let builders = match args.get_many::<String>("builder") {
+            Some(builders) => builders
+                .map(|builder| parse_url(builder))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![],
+        };
+
+        Ok(Some(Self {
+            builders,
+            target_blocks: parse_arg::<u64>(args, "target-blocks")?,
+            auth_signer,
+        }))
+    }
+
+    /// Submits the transactions to the builders given with `--builder`, or else to the ones of
+    /// the chain, for the blocks following `block_number`.
+    pub async fn submit(
+        &self,
+        chain: &ChainConfig,
+        transactions: &[SignedTransaction],
+        block_number: u64,
+    ) -> Result<Vec<BundleSubmission>, SimulationError> {
+        let builders = if self.builders.is_empty() {
+            chain
+                .builders
+                .iter()
+                .map(|builder| parse_url(builder))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            self.builders.clone()
+        };
+        if builders.is_empty() {
+            return Err(SimulationError::Config(format!(
+                "No builder configured for {}, use --builder or add them to the config file",
+                chain.name
+            )));
+        }
+
+        let clients = builders
+            .into_iter()
+            .map(|url| BuilderClient::new(url, self.auth_signer.clone()))
+            .collect::<Vec<_>>();
+        let raw_transactions = transactions
+            .iter()
+            .map(|transaction| transaction.raw.clone())
+            .collect::<Vec<_>>();
+
+        submit_bundle(
+            &clients,
+            &raw_transactions,
+            block_number + 1,
+            self.target_blocks,
+        )
+        .await
+    }
+}
+
+/// Fails when the swap would be signed without `--submit`. The input is transferred to the pool,
+/// or to the simulator, before swapping, and anyone can take it in between unless both
+/// transactions land in the same bundle.
+pub fn require_bundle_when_signing(
+    identity: Option<&Identity>,
+    bundle_options: Option<&BundleOptions>,
+) -> Result<(), SimulationError> {
+    if identity.and_then(Identity::signer).is_some() && bundle_options.is_none() {
+        return Err(SimulationError::Config(
+            "Signing the swap requires --submit, as the tokens transferred before swapping can \
+             be taken by anyone unless the swap lands in the same bundle. Use --from to simulate \
+             as the account without signing"
+                .to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_url(url: &str) -> Result<Url, SimulationError> {
+    url.parse::<Url>()
+        .map_err(|e| SimulationError::Config(format!("Invalid builder URL {url}: {e}")))
+}
+
+/// How the inclusion of the submitted transactions is monitored, `None` without `--ws-url`.
+pub struct MonitorOptions {
+    ws_url: String,
+    max_blocks: u64,
+}
+
+impl MonitorOptions {
+    pub fn from_args(args: &ArgMatches) -> Result<Option<Self>, SimulationError> {
+        if !args.contains_id("ws-url") {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            ws_url: parse_arg::<String>(args, "ws-url")?,
+            max_blocks: parse_arg::<u64>(args, "monitor-blocks")?,
+        }))
+    }
+
+    /// Subscribes to the new blocks. Must be done before submitting the transactions, so the
+    /// block including them cannot be missed.
+    pub async fn subscribe(&self) -> Result<Subscription<Header>, SimulationError> {
+        subscribe::<Header>(self.ws_url.clone(), EthWsSubscriptionRequest::new_heads(1)).await
+    }
+
+    pub fn max_blocks(&self) -> u64 {
+        self.max_blocks
+    }
+}
+
+/// Format used by the simulation commands to print their results, selected with the global
+/// `--output` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Text,
+}
+
+impl OutputFormat {
+    pub fn from_args(args: &ArgMatches) -> Self {
+        match args.get_one::<String>("output").map(String::as_str) {
+            Some("json") => Self::Json,
+            _ => Self::Text,
+        }
+    }
+
+    pub fn print<T: Serialize + Display>(&self, value: &T) {
+        match self {
+            Self::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(value).expect("Error serializing the result")
+            ),
+            Self::Text => println!("{value}"),
+        }
+    }
+}
+
 pub fn get_commands() -> HashMap<String, Box<dyn Command>> {
     let mut result = HashMap::new();
 
@@ -33,6 +403,7 @@ pub fn get_commands() -> HashMap<String, Box<dyn Command>> {
         Box::new(SubscribeNewBlockHeaders),
         Box::new(SubscribeNewPendingTransactions),
         Box::new(ComputeAddress),
+        Box::new(TokenSafety),
         Box::new(UniswapV2SwapViaRouter),
         Box::new(UniswapV2SwapViaPool),
         Box::new(UniswapV3SwapViaPool),
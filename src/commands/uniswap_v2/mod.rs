@@ -1,3 +1,2 @@
-mod contracts;
 pub mod swap_via_pool;
 pub mod swap_via_router;
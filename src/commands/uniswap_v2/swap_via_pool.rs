@@ -1,194 +1,79 @@
-use crate::commands::uniswap_v2::contracts::Pool;
-use crate::commands::Command;
-use crate::commons::erc20::Erc20;
-use crate::commons::helpers::{set_eth_balance, AlloyCacheDB};
-use alloy_eips::{BlockId, BlockNumberOrTag};
-use alloy_provider::Provider;
-use alloy_provider::{ProviderBuilder, RootProvider};
-use alloy_rpc_types::Block;
-use alloy_transport_http::Http;
-use anyhow::Result;
+use alloy_eips::BlockId;
+use alloy_provider::ProviderBuilder;
 use async_trait::async_trait;
+use clap::parser::ValueSource;
 use clap::{Arg, ArgAction, ArgMatches};
-use reqwest::Client;
-use revm::db::{AlloyDB, CacheDB};
-use revm::primitives::{address, Address, U256};
-use std::str::FromStr;
-
-#[derive(Debug, Clone)]
-pub(crate) struct SwapViaPoolConfig {
-    pool: Address,
-    token_in: Address,
-    amount: U256,
-    caller: Address,
-}
-
-impl SwapViaPoolConfig {
-    pub fn from_args(caller: Address, args: &ArgMatches) -> Self {
-        let pool = args
-            .get_one::<String>("pool")
-            .expect("Pool address is required");
-        let pool = Address::from_str(pool).expect("Invalid pool in address");
-
-        let token_in = args
-            .get_one::<String>("token-in")
-            .expect("Token in is required");
-        let token_in = Address::from_str(token_in).expect("Invalid token in address");
-
-        let amount = args
-            .get_one::<String>("amount")
-            .expect("Amount is required");
-        let amount = U256::from_str(amount).expect("Invalid amount");
-
-        Self {
-            pool,
-            token_in,
-            amount,
-            caller,
-        }
-    }
-}
-
-pub struct SwapViaPool;
-
-impl SwapViaPool {
-    fn simulate(
-        &self,
-        block: &Block,
-        client: &RootProvider<Http<Client>>,
-        swap_configuration: &SwapViaPoolConfig,
-    ) -> Result<(U256, U256)> {
-        let block_id = BlockId::Number(BlockNumberOrTag::Number(block.header.number));
-        let mut database = CacheDB::new(AlloyDB::new(client, block_id).unwrap());
-
-        let pool = Pool::new(swap_configuration.caller, swap_configuration.pool);
-        let pool_data = pool.get_pool_data(&mut database)?;
-
-        let token_out = if pool_data.token_0 == swap_configuration.token_in {
+use reqwest::Url;
+use revm::primitives::Address;
+use rust_revm_simulations::commons::{
+    fetch_block, monitor_inclusion, sign_steps, Identity, Registry, SimulationDatabase,
+    SimulationError,
+};
+use rust_revm_simulations::uniswap_v2::contracts::Pool;
+use rust_revm_simulations::uniswap_v2::pair::{find_pair, verify_pair};
+use rust_revm_simulations::uniswap_v2::swap_via_pool::{simulate, SwapViaPoolConfig};
+use rust_revm_simulations::uniswap_v2::v2_math::SwapFee;
+use rust_revm_simulations::SimulationSession;
+
+use crate::commands::{
+    allow_unknown_pool_arg, check_pool, identity_args, identity_from_args, load_registry,
+    parse_amount_arg, parse_arg, require_bundle_when_signing, select_chain, transaction_args,
+    transaction_settings_from_args, BundleOptions, Command, MonitorOptions, OutputFormat,
+};
+
+fn config_from_args<DB: SimulationDatabase>(
+    args: &ArgMatches,
+    registry: &Registry,
+    session: &mut SimulationSession<DB>,
+) -> Result<SwapViaPoolConfig, SimulationError> {
+    let token_in = parse_arg::<Address>(args, "token-in")?;
+    let chain_id = session.cfg_env().chain_id;
+
+    // Without a pool, the pair of the tokens is looked up for the selected DEX. A given pool must
+    // be a genuine pair of one of the known factories.
+    let (pool, token_out, factory) = if args.contains_id("token-out") {
+        let token_out = parse_arg::<Address>(args, "token-out")?;
+        let chain = select_chain(args, registry, chain_id)?;
+        let factory = chain
+            .uniswap_v2_factory(&parse_arg::<String>(args, "dex")?)?
+            .clone();
+        let pool = find_pair(session, &factory, token_in, token_out)?;
+        (pool, token_out, Some(factory))
+    } else {
+        let pool = parse_arg::<Address>(args, "pool")?;
+        let pool_data = Pool::new(pool).get_pool_data(session)?;
+        let verification = select_chain(args, registry, chain_id)
+            .and_then(|chain| verify_pair(pool, &pool_data, &chain.uniswap_v2_factories).cloned());
+        let factory = check_pool(args, verification)?;
+        let token_out = if pool_data.token_0 == token_in {
             pool_data.token_1
         } else {
             pool_data.token_0
         };
-
-        let token_in = Erc20::new(swap_configuration.caller, swap_configuration.token_in);
-        let token_out = Erc20::new(swap_configuration.caller, token_out);
-
-        // Optional step for convenience. Extreme caution is advised when using this method
-        // as this might lead to a simulation that does not match the real state of the blockchain.
-        set_eth_balance(
-            swap_configuration.caller,
-            swap_configuration.amount,
-            &mut database,
-        );
-        let _ = token_in.set_balance(
-            swap_configuration.caller,
-            swap_configuration.amount,
-            &mut database,
-        );
-
-        // Save the balances before perdorming the swap so that we can validate that the swap
-        // was successful.
-        let balance_in_before = token_in
-            .balance_of(swap_configuration.caller, &mut database)?
-            .0;
-        let balance_out_before = token_out
-            .balance_of(swap_configuration.caller, &mut database)?
-            .0;
-
-        let (amount0_out, amount1_out) =
-            self.get_output_amounts(swap_configuration, &mut database)?;
-
-        // Mandatory step, before executing the swap on the pool we need to transfer the correct input
-        // token amount to the pool. Without this step, the swap will fail with a `ISUFFICIENT
-        // LIQUIDITY` error.
-        let _ = token_in.transfer(
-            swap_configuration.pool,
-            swap_configuration.amount,
-            &mut database,
-        )?;
-
-        let _ = pool.swap(
-            amount0_out,
-            amount1_out,
-            swap_configuration.caller,
-            &mut database,
-        )?;
-
-        // In order to ensure the swap was successful, we need to check the balances of both tokens
-        // before and after the swap.
-        let balance_in_after = token_in
-            .balance_of(swap_configuration.caller, &mut database)?
-            .0;
-        let balance_out_after = token_out
-            .balance_of(swap_configuration.caller, &mut database)?
-            .0;
-
-        let amount_out = if pool_data.token_0 == swap_configuration.token_in {
-            amount1_out
-        } else {
-            amount0_out
-        };
-
-        assert!(
-            balance_in_before - swap_configuration.amount == balance_in_after,
-            "The balance of token in does not match the expected output"
-        );
-        assert!(
-            balance_out_before + amount_out == balance_out_after,
-            "The balance of token out does not match the expected output"
-        );
-
-        // TODO: For now we are just returning the input and output amounts, which is fine and works.
-        // However, that means we will have to build the transaction again to send it to builders.
-        // An alternative approach would be to build the final transaction, simulate it and if ok
-        // return the transaction ready to be sent.
-        Ok((swap_configuration.amount, amount_out))
-    }
-
-    fn get_output_amounts(
-        &self,
-        swap_configuration: &SwapViaPoolConfig,
-        database: &mut AlloyCacheDB,
-    ) -> Result<(U256, U256)> {
-        // In UniswapV2 protocol, the fee is harcoded to be 0.3%. Since pools are deployed via a
-        // well-known factory (0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f) also known as Uniswap V2 Deployer,
-        // we can safely assume the fee is always 0.3%.
-        // Ideally, we should always validate the factory that deployed the pool, not only to ensure
-        // the fee is correct, but also to be sure the pool is not a malicious one.
-        //
-        // For more information check the contract code at: https://github.com/Uniswap/v2-periphery/blob/0335e8f7e1bd1e8d8329fd300aea2ef2f36dd19f/contracts/libraries/UniswapV2Library.sol#L43
-        let fee = U256::from(3);
-        let percentage = U256::from(1000);
-
-        let pool = Pool::new(swap_configuration.caller, swap_configuration.pool);
-
-        let (reserve_0, reserve_1) = pool.get_reserves(database)?;
-        let pool_data = pool.get_pool_data(database)?;
-
-        let (reserve_input, reserver_output) = if pool_data.token_0 == swap_configuration.token_in {
-            (reserve_0, reserve_1)
-        } else {
-            (reserve_1, reserve_0)
-        };
-
-        let amount_in_with_fee = swap_configuration
-            .amount
-            .saturating_mul(percentage.saturating_sub(fee)); // Amoun * (1000 - fee)
-        let numerator = amount_in_with_fee.saturating_mul(reserver_output);
-        let denominator = reserve_input
-            .saturating_mul(percentage)
-            .saturating_add(amount_in_with_fee);
-
-        let amount_out = numerator.checked_div(denominator).unwrap();
-
-        if pool_data.token_0 == swap_configuration.token_in {
-            Ok((U256::ZERO, amount_out))
-        } else {
-            Ok((amount_out, U256::ZERO))
-        }
-    }
+        (pool, token_out, factory)
+    };
+
+    // The fee of the factory of the pool, unless `--dex` says otherwise, or the 0.3% of Uniswap
+    // for an unknown pool.
+    let fee = if args.value_source("dex") == Some(ValueSource::CommandLine) {
+        select_chain(args, registry, chain_id)?
+            .uniswap_v2_factory(&parse_arg::<String>(args, "dex")?)?
+            .fee
+    } else {
+        factory.map_or(SwapFee::default(), |factory| factory.fee)
+    };
+
+    let exact_out = args.get_flag("exact-out");
+    let amount_token = if exact_out { token_out } else { token_in };
+    let amount = parse_amount_arg(args, "amount", amount_token, session)?;
+
+    Ok(SwapViaPoolConfig::new(pool, token_in, amount)
+        .with_fee(fee)
+        .with_exact_out(exact_out))
 }
 
+pub struct SwapViaPool;
+
 #[async_trait]
 impl Command for SwapViaPool {
     fn create(&self) -> clap::Command {
@@ -202,6 +87,13 @@ impl Command for SwapViaPool {
                     .required(true)
                     .help("The RPC URL to connect to"),
             )
+            .arg(
+                Arg::new("block")
+                    .long("block")
+                    .help("The block to fork from, either a number, a hash or a tag (latest, safe, finalized)")
+                    .default_value("latest")
+                    .action(ArgAction::Set),
+            )
             .arg(
                 Arg::new("token-in")
                     .long("token-in")
@@ -213,52 +105,104 @@ impl Command for SwapViaPool {
                 Arg::new("pool")
                     .long("pool")
                     .help("The pool address")
-                    .required(true)
+                    .required_unless_present("token-out")
+                    .conflicts_with("token-out")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("token-out")
+                    .long("token-out")
+                    .help("The token to swap to, whose pair with token in is looked up instead of giving --pool")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("dex")
+                    .long("dex")
+                    .help("The Uniswap V2 fork whose pair is looked up with --token-out, among the factories of the registry. Also sets the fee of the pool")
+                    .default_value("uniswap")
                     .action(ArgAction::Set),
             )
             .arg(
                 Arg::new("amount")
                     .long("amount")
-                    .help("The amount of token in to swap")
+                    .help("The amount of token in to swap, or of token out to receive with --exact-out, either raw (1500000) or in token units when it has a decimal point (1.5)")
                     .required(true)
                     .action(ArgAction::Set),
             )
+            .arg(
+                Arg::new("exact-out")
+                    .long("exact-out")
+                    .help("Buy exactly --amount of token out instead of selling --amount of token in")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(allow_unknown_pool_arg())
+            .args(identity_args())
+            .args(transaction_args())
     }
 
     fn name(&self) -> String {
         "swap-via-pool".to_owned()
     }
 
-    async fn execute(&self, args: &ArgMatches) {
-        let rpc_url = args
-            .get_one::<String>("rpc-url")
-            .expect("RPC URL is required");
-        let rpc_url = rpc_url.parse().unwrap();
+    async fn execute(&self, args: &ArgMatches) -> Result<(), SimulationError> {
+        let rpc_url = parse_arg::<Url>(args, "rpc-url")?;
+        let block_id = parse_arg::<BlockId>(args, "block")?;
+        let registry = load_registry(args)?;
+        let identity = identity_from_args(args)?;
+        let bundle_options = BundleOptions::from_args(args, identity.as_ref())?;
+        let monitor_options = MonitorOptions::from_args(args)?;
+        require_bundle_when_signing(identity.as_ref(), bundle_options.as_ref())?;
 
         let client = ProviderBuilder::new().on_http(rpc_url);
-        let block = client
-            .get_block_by_number(BlockNumberOrTag::Latest, true)
-            .await
-            .unwrap()
-            .unwrap();
+        let block = fetch_block(&client, block_id).await?;
 
         // The caller must be the public address that will sign the transactions,
-        // which implies this wallet must be funded. For the purposes of this example
-        // we are using a random address that is funded when simulating.
-        let caller = address!("FF3cF7b8582571095A2B05268A4E1BafBDAD060D");
+        // which implies this wallet must be funded. When no identity is given we are using
+        // a random address that is funded when simulating.
+        let caller = identity
+            .as_ref()
+            .map_or(registry.caller, |identity| identity.address());
+
+        // Step 1: Simulate the swap hitting the pair straight away: the input is transferred to
+        // the pair and the output computed from its reserves is requested with `swap`.
+        let mut session = SimulationSession::fork(&client, &block, caller)
+            .await?
+            .with_balance_check(identity.is_some());
+        let swap_configuration =
+            config_from_args(args, &registry, &mut session)?.with_fund_caller(identity.is_none());
+        let nonce = session.nonce(caller)?;
+        let mut result = simulate(&mut session, &swap_configuration)?;
+
+        // Step 2: Build the final transactions replaying the swap and sign them.
+        if let Some(signer) = identity.as_ref().and_then(Identity::signer) {
+            let settings = transaction_settings_from_args(args)?;
+            result.transactions = sign_steps(&session, &result.steps, nonce, &settings, signer)?;
+        }
 
-        let swap_configuration = SwapViaPoolConfig::from_args(caller, args);
+        // Step 3: Send the transactions to builders.
+        let mut new_heads = None;
+        if let Some(bundle_options) = bundle_options {
+            if let Some(monitor_options) = &monitor_options {
+                new_heads = Some(monitor_options.subscribe().await?);
+            }
+
+            let chain = select_chain(args, &registry, session.cfg_env().chain_id)?;
+            let block_number = session.block().header.number;
+            result.bundles = bundle_options
+                .submit(&chain, &result.transactions, block_number)
+                .await?;
+        }
 
-        // Step 1: Based on the discovery made with the router, we know simulate the swap hitting
-        // the poool straight away.
-        let result = self
-            .simulate(&block, &client, &swap_configuration)
-            .expect("Error running simulation for pool");
+        // Step 4: Monitor the chain until we find our transactions in a block.
+        if let (Some(new_heads), Some(monitor_options)) = (new_heads, &monitor_options) {
+            let inclusion =
+                monitor_inclusion(&client, new_heads, &result, monitor_options.max_blocks())
+                    .await?;
+            result.inclusion = Some(inclusion);
+        }
 
-        println!("Swap Via Pool - Result: \n{:#?}", result);
+        OutputFormat::from_args(args).print(&result);
 
-        // Step 3: Build the final transaction and send it to builders.
-        // Step 4: Monitor the chain until we find our transaction in a block
-        // WIP
+        Ok(())
     }
 }
@@ -1,222 +1,55 @@
-use alloy_eips::{BlockId, BlockNumberOrTag};
-use alloy_provider::Provider;
-use alloy_provider::{network::Ethereum, ProviderBuilder, RootProvider};
-use alloy_rpc_types::Block;
-use alloy_transport_http::Http;
-use anyhow::{anyhow, Result};
+use alloy_eips::BlockId;
+use alloy_provider::ProviderBuilder;
 use async_trait::async_trait;
 use clap::{Arg, ArgAction, ArgMatches};
-use reqwest::Client;
-use revm::db::{AlloyDB, CacheDB};
-use revm::primitives::{address, Address, Log, U256};
-use std::str::FromStr;
-
-use crate::commands::uniswap_v2::contracts::Pool;
-use crate::commands::Command;
-use crate::commons::erc20::Erc20;
-use crate::commons::helpers::set_eth_balance;
-
-use super::contracts::Router;
-
-#[derive(Debug, Clone)]
-struct SwapViaRouterConfig {
-    token_in: Address,
-    token_out: Address,
-    amount: U256,
-    caller: Address,
-}
-
-impl SwapViaRouterConfig {
-    pub fn from_args(caller: Address, args: &ArgMatches) -> Self {
-        let token_in = args
-            .get_one::<String>("token-in")
-            .expect("Token in is required");
-        let token_in = Address::from_str(token_in).expect("Invalid token in address");
-
-        let token_out = args
-            .get_one::<String>("token-out")
-            .expect("Token out is required");
-        let token_out = Address::from_str(token_out).expect("Invalid token out address");
-
-        let amount = args
-            .get_one::<String>("amount")
-            .expect("Amount is required");
-        let amount = U256::from_str(amount).expect("Invalid amount");
-
-        Self {
-            token_in,
-            token_out,
-            amount,
-            caller,
-        }
-    }
+use reqwest::Url;
+use revm::primitives::Address;
+use rust_revm_simulations::commons::{
+    fetch_block, monitor_inclusion, sign_steps, ChainConfig, Identity, SimulationDatabase,
+    SimulationError,
+};
+use rust_revm_simulations::uniswap_v2::swap_via_router::{
+    simulate_with_pool, simulate_with_router, SwapViaRouterConfig,
+};
+use rust_revm_simulations::SimulationSession;
+
+use crate::commands::{
+    identity_args, identity_from_args, load_registry, parse_amount_arg, parse_arg,
+    require_bundle_when_signing, select_chain, transaction_args, transaction_settings_from_args,
+    BundleOptions, Command, MonitorOptions, OutputFormat,
+};
+
+fn config_from_args<DB: SimulationDatabase>(
+    args: &ArgMatches,
+    chain: &ChainConfig,
+    session: &mut SimulationSession<DB>,
+) -> Result<SwapViaRouterConfig, SimulationError> {
+    let token_in = parse_arg::<Address>(args, "token-in")?;
+    let token_out = parse_arg::<Address>(args, "token-out")?;
+    let via = match args.get_many::<String>("via") {
+        Some(tokens) => tokens
+            .map(|token| {
+                token
+                    .parse::<Address>()
+                    .map_err(|e| SimulationError::Config(format!("Invalid --via {token}: {e}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![],
+    };
+    let exact_out = args.get_flag("exact-out");
+    let amount_token = if exact_out { token_out } else { token_in };
+    let amount = parse_amount_arg(args, "amount", amount_token, session)?;
+
+    Ok(
+        SwapViaRouterConfig::new(chain.uniswap_v2_router, token_in, token_out, amount)
+            .with_via(via)
+            .with_exact_out(exact_out)
+            .with_override_allowance(args.get_flag("skip-approval")),
+    )
 }
 
 pub struct SwapViaRouter;
 
-impl SwapViaRouter {
-    fn simulate_with_router(
-        &self,
-        block: &Block,
-        client: &RootProvider<Http<Client>>,
-        swap_configuration: &SwapViaRouterConfig,
-    ) -> Result<Vec<Log>> {
-        let block_id = BlockId::Number(BlockNumberOrTag::Number(block.header.number));
-        let database: AlloyDB<Http<Client>, Ethereum, &RootProvider<Http<Client>>> =
-            AlloyDB::new(client, block_id).unwrap();
-        let mut database = CacheDB::new(database);
-
-        // This is the address of UniswapV2 Router at Ethereum mainnet. If testing in a
-        // different chain, please change this address accordingly.
-        let router = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
-
-        let token = Erc20::new(swap_configuration.caller, swap_configuration.token_in);
-
-        // Optional step for convenience. Extreme caution is advised when using this method
-        // as this might lead to a simulation that does not match the real state of the blockchain.
-        set_eth_balance(
-            swap_configuration.caller,
-            swap_configuration.amount,
-            &mut database,
-        );
-        let _ = token.set_balance(
-            swap_configuration.caller,
-            swap_configuration.amount,
-            &mut database,
-        );
-
-        // Mandatory step, the router performs a `transfer_from` call pon the Erc20 on behalf of the
-        // caller. Without this step, the swap will fail with a `TRANSFER_FROM_FAILED` error.
-        let _ = token.approve(router, swap_configuration.amount, &mut database);
-
-        let router = Router::new(swap_configuration.caller, router);
-
-        let path = vec![swap_configuration.token_in, swap_configuration.token_out];
-
-        // The amount_out_min indicates the router the minimum amount of output tokens expected.
-        // The router will fail the swap if the output amount is smaller than this. For our purposes
-        // we set it to zero as we are only running a simulation.
-        let amount_out_min = U256::ZERO;
-
-        // The deadline, expressed in timestamp, indicates the number of blocks allowed to process the
-        // swap. Since there is a new block produced each 12 seconds, we are specifying that the swap
-        // is only valid for the next 3 blocks.
-        let deadline = U256::from(block.header.timestamp + 36);
-
-        let result = router.swap_exact_tokens_for_tokens(
-            swap_configuration.amount,
-            amount_out_min,
-            path,
-            swap_configuration.caller,
-            deadline,
-            &mut database,
-        )?;
-
-        Ok(result.1)
-    }
-
-    fn simulate_with_pool(
-        &self,
-        block: &Block,
-        client: &RootProvider<Http<Client>>,
-        logs: Vec<Log>,
-        swap_configuration: &SwapViaRouterConfig,
-    ) -> Result<(U256, U256)> {
-        let block_id = BlockId::Number(BlockNumberOrTag::Number(block.header.number));
-        let mut database = CacheDB::new(AlloyDB::new(client, block_id).unwrap());
-
-        let swap_logs = Pool::decode_swaps(&logs)?;
-
-        // For the purposes of this example we are only moving forward when there is a single swap.
-        // However, there can be cases where the router performed multiple swaps to get the output.
-        // This can happen when there is no direct path between two pairs for example.
-        if swap_logs.len() != 1 {
-            return Err(anyhow!("Only one swap is supported"));
-        }
-
-        let swap_event = swap_logs[0].clone();
-
-        let pool = Pool::new(swap_configuration.caller, swap_event.pool);
-
-        let token_in = Erc20::new(swap_configuration.caller, swap_configuration.token_in);
-        let token_out = Erc20::new(swap_configuration.caller, swap_configuration.token_out);
-
-        // Optional step for convenience. Extreme caution is advised when using this method
-        // as this might lead to a simulation that does not match the real state of the blockchain.
-        set_eth_balance(
-            swap_configuration.caller,
-            swap_configuration.amount,
-            &mut database,
-        );
-        let _ = token_in.set_balance(
-            swap_configuration.caller,
-            swap_configuration.amount,
-            &mut database,
-        );
-
-        // Save the balances before perdorming the swap so that we can validate that the swap
-        // was successful.
-        let balance_in_before = token_in
-            .balance_of(swap_configuration.caller, &mut database)?
-            .0;
-        let balance_out_before = token_out
-            .balance_of(swap_configuration.caller, &mut database)?
-            .0;
-
-        // Pools have `token0` and `token1`, we are not analyzing the pool, which means we don't
-        // know which token we are swapping for. An alternative to get that information without
-        // analyzing the pool is to check the Swap event and see which input amount (`amount0_in` or
-        // `amount1_in`) is not zero.
-        let amount_in = if swap_event.amount0_in.is_zero() {
-            swap_event.amount1_in
-        } else {
-            swap_event.amount0_in
-        };
-
-        // Mandatory step, before executing the swap on the pool we need to transfer the correct input
-        // token amount to the pool. Without this step, the swap will fail with a `IIA` (Insufficient
-        // Input Amount) error.
-        let _ = token_in.transfer(swap_event.pool, amount_in, &mut database)?;
-
-        let _ = pool.swap(
-            swap_event.amount0_out,
-            swap_event.amount1_out,
-            swap_configuration.caller,
-            &mut database,
-        )?;
-
-        // In order to ensure the swap was successful, we need to check the balances of both tokens
-        // before and after the swap.
-        let balance_in_after = token_in
-            .balance_of(swap_configuration.caller, &mut database)?
-            .0;
-        let balance_out_after = token_out
-            .balance_of(swap_configuration.caller, &mut database)?
-            .0;
-
-        let amount_out = if swap_event.amount0_out.is_zero() {
-            swap_event.amount1_out
-        } else {
-            swap_event.amount0_out
-        };
-
-        assert!(
-            balance_in_before - amount_in == balance_in_after,
-            "The balance of token in does not match the expected output"
-        );
-        assert!(
-            balance_out_before + amount_out == balance_out_after,
-            "The balance of token out does not match the expected output"
-        );
-
-        // TODO: For now we are just returning the input and output amounts, which is fine and works.
-        // However, that means we will have to build the transaction again to send it to builders.
-        // An alternative approach would be to build the final transaction, simulate it and if ok
-        // return the transaction ready to be sent.
-        Ok((amount_in, amount_out))
-    }
-}
-
 #[async_trait]
 impl Command for SwapViaRouter {
     fn create(&self) -> clap::Command {
@@ -230,6 +63,13 @@ impl Command for SwapViaRouter {
                     .required(true)
                     .help("The RPC URL to connect to"),
             )
+            .arg(
+                Arg::new("block")
+                    .long("block")
+                    .help("The block to fork from, either a number, a hash or a tag (latest, safe, finalized)")
+                    .default_value("latest")
+                    .action(ArgAction::Set),
+            )
             .arg(
                 Arg::new("token-in")
                     .long("token-in")
@@ -244,53 +84,112 @@ impl Command for SwapViaRouter {
                     .required(true)
                     .action(ArgAction::Set),
             )
+            .arg(
+                Arg::new("via")
+                    .long("via")
+                    .help("A token to swap through between token in and token out. Can be repeated, in order")
+                    .action(ArgAction::Append),
+            )
             .arg(
                 Arg::new("amount")
                     .long("amount")
-                    .help("The amount of token in to swap")
+                    .help("The amount of token in to swap, or of token out to receive with --exact-out, either raw (1500000) or in token units when it has a decimal point (1.5)")
                     .required(true)
                     .action(ArgAction::Set),
             )
+            .arg(
+                Arg::new("exact-out")
+                    .long("exact-out")
+                    .help("Buy exactly --amount of token out instead of selling --amount of token in")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("skip-approval")
+                    .long("skip-approval")
+                    .help("Write the allowance of the router in the token storage instead of simulating the approval")
+                    .action(ArgAction::SetTrue),
+            )
+            .args(identity_args())
+            .args(transaction_args())
     }
 
     fn name(&self) -> String {
         "swap-via-router".to_owned()
     }
 
-    async fn execute(&self, args: &ArgMatches) {
-        let rpc_url = args
-            .get_one::<String>("rpc-url")
-            .expect("RPC URL is required");
-        let rpc_url = rpc_url.parse().unwrap();
+    async fn execute(&self, args: &ArgMatches) -> Result<(), SimulationError> {
+        let rpc_url = parse_arg::<Url>(args, "rpc-url")?;
+        let block_id = parse_arg::<BlockId>(args, "block")?;
+        let registry = load_registry(args)?;
+        let identity = identity_from_args(args)?;
+        let bundle_options = BundleOptions::from_args(args, identity.as_ref())?;
+        let monitor_options = MonitorOptions::from_args(args)?;
+        require_bundle_when_signing(identity.as_ref(), bundle_options.as_ref())?;
 
         let client = ProviderBuilder::new().on_http(rpc_url);
-        let block = client
-            .get_block_by_number(BlockNumberOrTag::Latest, true)
-            .await
-            .unwrap()
-            .unwrap();
+        let block = fetch_block(&client, block_id).await?;
 
         // The caller must be the public address that will sign the transactions,
-        // which implies this wallet must be funded. For the purposes of this example
-        // we are using a random address that is funded when simulating.
-        let caller = address!("FF3cF7b8582571095A2B05268A4E1BafBDAD060D");
-
-        let swap_configuration = SwapViaRouterConfig::from_args(caller, args);
+        // which implies this wallet must be funded. When no identity is given we are using
+        // a random address that is funded when simulating.
+        let caller = identity
+            .as_ref()
+            .map_or(registry.caller, |identity| identity.address());
 
         // Step 1: Simulate the swap via the router and extract the Swap events that contains
         // the pool address and the amount of tokens swapped.
-        let logs = self
-            .simulate_with_router(&block, &client, &swap_configuration)
-            .expect("Error running simulation for router");
+        let mut session = SimulationSession::fork(&client, &block, caller)
+            .await?
+            .with_balance_check(identity.is_some());
+        let chain = select_chain(args, &registry, session.cfg_env().chain_id)?;
+        let swap_configuration =
+            config_from_args(args, &chain, &mut session)?.with_fund_caller(identity.is_none());
+        let router_swap = simulate_with_router(&mut session, &swap_configuration)?;
 
         // Step 2: Based on the discovery made with the router, we know simulate the swap hitting
-        // the poool straiught away.
-        let (_amount_in, _amount_out) = self
-            .simulate_with_pool(&block, &client, logs, &swap_configuration)
-            .expect("Error running simulation for pool");
+        // the poool straiught away. A fresh fork is used so the router swap is not accounted.
+        let mut session = SimulationSession::fork(&client, &block, caller)
+            .await?
+            .with_balance_check(identity.is_some());
+        let nonce = session.nonce(caller)?;
+        let mut result = simulate_with_pool(&mut session, &router_swap, &swap_configuration)?;
+
+        // Step 3: Build the final transactions replaying the swap and sign them.
+        if let Some(signer) = identity.as_ref().and_then(Identity::signer) {
+            let settings = transaction_settings_from_args(args)?;
+            result.transactions = sign_steps(&session, &result.steps, nonce, &settings, signer)?;
+        }
+        let mut router_steps = router_swap
+            .approvals
+            .into_iter()
+            .map(|approval| approval.into_step("Router Approval"))
+            .collect::<Vec<_>>();
+        router_steps.push(router_swap.swap.into_step("Router Swap"));
+        result.steps.splice(0..0, router_steps);
+
+        // Step 4: Send the transactions to builders.
+        let mut new_heads = None;
+        if let Some(bundle_options) = bundle_options {
+            if let Some(monitor_options) = &monitor_options {
+                new_heads = Some(monitor_options.subscribe().await?);
+            }
+
+            let block_number = session.block().header.number;
+            result.bundles = bundle_options
+                .submit(&chain, &result.transactions, block_number)
+                .await?;
+        }
+
+        // Step 5: Monitor the chain until we find our transactions in a block.
+        if let (Some(new_heads), Some(monitor_options)) = (new_heads, &monitor_options) {
+            let inclusion =
+                monitor_inclusion(&client, new_heads, &result, monitor_options.max_blocks())
+                    .await?;
+            result.inclusion = Some(inclusion);
+        }
+
+        OutputFormat::from_args(args).print(&result);
 
-        // Step 3: Build the final transaction and send it to builders.
-        // Step 4: Monitor the chain until we find our transaction in a block
-        // WIP
+        Ok(())
     }
 }
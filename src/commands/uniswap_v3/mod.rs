@@ -1,2 +1 @@
-mod contracts;
 pub mod swap_via_pool;
@@ -0,0 +1,103 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use revm::interpreter::{opcode, CreateInputs, CreateOutcome, Interpreter};
+use revm::primitives::{AccessList, AccessListItem, Address, SpecId, B256};
+use revm::{Database, EvmContext, Inspector};
+
+/// Inspector that records the accounts and storage slots accessed by a transaction, in order to
+/// build its EIP-2930 access list.
+///
+/// Addresses that are warm by default (the caller, the target, the coinbase, the precompiles and
+/// any contract created by the transaction) are left out, as listing them would only add to the
+/// intrinsic cost of the transaction without saving any cold access.
+#[derive(Debug, Default)]
+pub struct AccessListInspector {
+    accessed: BTreeMap<Address, BTreeSet<B256>>,
+    created: HashSet<Address>,
+}
+
+impl AccessListInspector {
+    pub fn into_access_list(self) -> AccessList {
+        let items = self
+            .accessed
+            .into_iter()
+            .filter(|(address, _)| !self.created.contains(address))
+            .map(|(address, storage_keys)| AccessListItem {
+                address,
+                storage_keys: storage_keys.into_iter().collect(),
+            })
+            .collect::<Vec<_>>();
+
+        AccessList::from(items)
+    }
+
+    fn record_account<DB: Database>(&mut self, address: Address, context: &EvmContext<DB>) {
+        if !is_warm_by_default(address, context) {
+            self.accessed.entry(address).or_default();
+        }
+    }
+
+    fn record_slot<DB: Database>(
+        &mut self,
+        address: Address,
+        slot: B256,
+        context: &EvmContext<DB>,
+    ) {
+        if !is_warm_by_default(address, context) {
+            self.accessed.entry(address).or_default().insert(slot);
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for AccessListInspector {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        let stack = interp.stack();
+
+        match interp.current_opcode() {
+            opcode::SLOAD | opcode::SSTORE => {
+                if let Ok(slot) = stack.peek(0) {
+                    let address = interp.contract.target_address;
+                    self.record_slot(address, B256::from(slot), context);
+                }
+            }
+            opcode::BALANCE
+            | opcode::EXTCODESIZE
+            | opcode::EXTCODECOPY
+            | opcode::EXTCODEHASH
+            | opcode::SELFDESTRUCT => {
+                if let Ok(address) = stack.peek(0) {
+                    self.record_account(Address::from_word(B256::from(address)), context);
+                }
+            }
+            opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL => {
+                if let Ok(address) = stack.peek(1) {
+                    self.record_account(Address::from_word(B256::from(address)), context);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        if let Some(address) = outcome.address {
+            self.created.insert(address);
+        }
+
+        outcome
+    }
+}
+
+// The coinbase is only warm since Shanghai (EIP-3651).
+fn is_warm_by_default<DB: Database>(address: Address, context: &EvmContext<DB>) -> bool {
+    let env = &context.env;
+
+    address == env.tx.caller
+        || env.tx.transact_to.to() == Some(&address)
+        || (address == env.block.coinbase && context.spec_id().is_enabled_in(SpecId::SHANGHAI))
+        || context.precompiles.contains(&address)
+}
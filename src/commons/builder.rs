@@ -0,0 +1,215 @@
+use std::fmt;
+
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use reqwest::{Client, Url};
+use revm::primitives::{hex, keccak256, Bytes, B256};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::errors::{Result, SimulationError};
+use super::identity::compute_address;
+
+/// Header carrying the signature of the request body, which builders use to identify the sender
+/// of the bundles and keep track of its reputation.
+pub const FLASHBOTS_SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SendBundleParams<'a> {
+    txs: &'a [Bytes],
+    /// Hex encoded, as every quantity in JSON-RPC.
+    block_number: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendBundleResult {
+    bundle_hash: Option<B256>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse {
+    result: Option<Value>,
+    error: Option<JsonRpcError>,
+}
+
+/// Outcome of sending a bundle to a builder for a given block.
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleSubmission {
+    pub builder: String,
+    pub block_number: u64,
+    /// Hash of the bundle, for the builders that return one.
+    pub bundle_hash: Option<B256>,
+    /// Why the builder refused the bundle, or why it could not be reached.
+    pub error: Option<String>,
+}
+
+impl BundleSubmission {
+    pub fn is_accepted(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl fmt::Display for BundleSubmission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let builder = &self.builder;
+        let block_number = self.block_number;
+
+        match (&self.error, self.bundle_hash) {
+            (Some(error), _) => write!(f, "Bundle for block {block_number} - {builder}: {error}"),
+            (None, Some(bundle_hash)) => write!(
+                f,
+                "Bundle for block {block_number} - {builder}: accepted ({bundle_hash})"
+            ),
+            (None, None) => write!(f, "Bundle for block {block_number} - {builder}: accepted"),
+        }
+    }
+}
+
+/// Client of a builder endpoint accepting Flashbots style `eth_sendBundle` requests.
+#[derive(Debug, Clone)]
+pub struct BuilderClient {
+    client: Client,
+    url: Url,
+    auth_signer: PrivateKeySigner,
+}
+
+impl BuilderClient {
+    /// Creates a client signing its requests with `auth_signer`. The key only identifies the
+    /// sender of the bundles, it does not need to hold any funds.
+    pub fn new(url: Url, auth_signer: PrivateKeySigner) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            auth_signer,
+        }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Sends the signed `transactions` as a bundle to be included in `block_number`. The
+    /// transactions are included in the given order, and the bundle is discarded when any of
+    /// them reverts.
+    pub async fn send_bundle(
+        &self,
+        transactions: &[Bytes],
+        block_number: u64,
+    ) -> Result<Option<B256>> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendBundle",
+            "params": [SendBundleParams {
+                txs: transactions,
+                block_number: format!("{block_number:#x}"),
+            }],
+        });
+        let body = serde_json::to_string(&request)
+            .map_err(|e| SimulationError::Decoding(e.to_string()))?;
+        let signature = self.sign_body(&body)?;
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(FLASHBOTS_SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await
+            .map_err(SimulationError::rpc)?;
+
+        let status = response.status();
+        let text = response.text().await.map_err(SimulationError::rpc)?;
+        let response = serde_json::from_str::<JsonRpcResponse>(&text).map_err(|_| {
+            SimulationError::Rpc(format!(
+                "Unexpected response ({status}) from the builder: {text}"
+            ))
+        })?;
+
+        if let Some(error) = response.error {
+            return Err(SimulationError::Rpc(format!(
+                "The builder refused the bundle: {} ({})",
+                error.message, error.code
+            )));
+        }
+
+        // Some builders answer with an empty result instead of the bundle hash.
+        let bundle_hash = response
+            .result
+            .and_then(|result| serde_json::from_value::<SendBundleResult>(result).ok())
+            .and_then(|result| result.bundle_hash);
+
+        Ok(bundle_hash)
+    }
+
+    /// Builds the value of the signature header: `<address>:<signature>`, where the signature is
+    /// the EIP-191 signature of the hex encoded hash of the body.
+    fn sign_body(&self, body: &str) -> Result<String> {
+        let body_hash = keccak256(body.as_bytes()).to_string();
+
+        let signature = self
+            .auth_signer
+            .sign_message_sync(body_hash.as_bytes())
+            .map_err(|e| SimulationError::Config(format!("Cannot sign the bundle: {e}")))?;
+
+        // The recovery id is expected as 27 or 28.
+        let mut signature_bytes = [0u8; 65];
+        signature_bytes[..32].copy_from_slice(&signature.r().to_be_bytes::<32>());
+        signature_bytes[32..64].copy_from_slice(&signature.s().to_be_bytes::<32>());
+        signature_bytes[64] = 27 + u8::from(signature.v().y_parity());
+
+        Ok(format!(
+            "{}:{}",
+            compute_address(&self.auth_signer),
+            hex::encode_prefixed(signature_bytes)
+        ))
+    }
+}
+
+/// Sends the bundle to every builder for each of the `blocks` blocks starting at `first_block`.
+///
+/// A builder refusing the bundle does not prevent sending it to the others, hence every
+/// submission is reported, and it only fails when no builder accepted the bundle.
+pub async fn submit_bundle(
+    builders: &[BuilderClient],
+    transactions: &[Bytes],
+    first_block: u64,
+    blocks: u64,
+) -> Result<Vec<BundleSubmission>> {
+    let mut submissions = vec![];
+
+    for block_number in first_block..first_block + blocks {
+        for builder in builders {
+            let result = builder.send_bundle(transactions, block_number).await;
+
+            submissions.push(BundleSubmission {
+                builder: builder.url().to_string(),
+                block_number,
+                bundle_hash: result.as_ref().ok().copied().flatten(),
+                error: result.err().map(|error| error.to_string()),
+            });
+        }
+    }
+
+    if !submissions.iter().any(BundleSubmission::is_accepted) {
+        let errors = submissions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(SimulationError::Rpc(format!(
+            "No builder accepted the bundle\n{errors}"
+        )));
+    }
+
+    Ok(submissions)
+}
@@ -1,13 +1,13 @@
-use alloy_sol_types::{sol, SolCall, SolValue};
-use anyhow::{anyhow, Result};
-use revm::{
-    primitives::{keccak256, AccessList, Address, Bytes, TxKind, U256},
-    Evm,
-};
+use alloy_sol_types::{sol, SolCall, SolEvent, SolValue};
+use revm::primitives::{Address, Bytes, Log, B256, U256};
+use serde::Serialize;
 
-use crate::commons::helpers::{extract_access_list, extract_gas_output_and_logs};
-
-use super::helpers::AlloyCacheDB;
+use crate::commons::errors::{Result, SimulationError};
+use crate::commons::helpers::SimulationDatabase;
+use crate::commons::results::SimulationOutcome;
+use crate::commons::session::SimulationSession;
+use crate::commons::storage::{find_mapping, StorageMapping};
+use crate::commons::units::{format_units, parse_units};
 
 sol! {
     #[allow(missing_docs)]
@@ -45,241 +45,272 @@ sol! {
     }
 }
 
+/// Name, symbol and decimals of a token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl TokenMetadata {
+    /// Formats a raw amount along with its value in token units, e.g. `1500000 (1.5 USDC)`.
+    pub fn format_amount(&self, amount: U256) -> String {
+        format!(
+            "{amount} ({} {})",
+            format_units(amount, self.decimals),
+            self.symbol
+        )
+    }
+}
+
 pub struct Erc20 {
-    caller: Address,
     token: Address,
 }
 
 impl Erc20 {
-    pub fn new(caller: Address, token: Address) -> Self {
-        Self { caller, token }
+    pub fn new(token: Address) -> Self {
+        Self { token }
+    }
+
+    pub fn address(&self) -> Address {
+        self.token
     }
 
-    pub fn balance_of(
+    /// Reads the name, symbol and decimals of the token, caching them in the session.
+    ///
+    /// The name and the symbol are optional in the standard, hence they are left empty when the
+    /// token does not implement them. Old tokens returning them as `bytes32` are supported too.
+    pub fn metadata<DB: SimulationDatabase>(
         &self,
-        account: Address,
-        database: &mut AlloyCacheDB,
-    ) -> Result<(U256, AccessList)> {
-        let calldata = Bytes::from(IErc20::balanceOfCall::new((account,)).abi_encode());
-        let mut evm = Evm::builder()
-            .with_db(database)
-            .modify_tx_env(|tx| {
-                tx.caller = self.caller;
-                tx.transact_to = TxKind::Call(self.token);
-                tx.data = calldata;
-                tx.value = U256::from(0);
+        session: &mut SimulationSession<DB>,
+    ) -> Result<TokenMetadata> {
+        if let Some(metadata) = session.token_metadata(self.token) {
+            return Ok(metadata.clone());
+        }
+
+        let decimals_call = Bytes::from(IErc20::decimalsCall::new(()).abi_encode());
+        let decimals = session
+            .call(self.token, decimals_call, |output| {
+                Ok(IErc20::decimalsCall::abi_decode_returns(output, false)?._0)
             })
-            .build();
+            .map_err(|e| {
+                SimulationError::Decoding(format!(
+                    "Cannot read the decimals of {}: {e}",
+                    self.token
+                ))
+            })?
+            .decoded;
+
+        let name_call = Bytes::from(IErc20::nameCall::new(()).abi_encode());
+        let symbol_call = Bytes::from(IErc20::symbolCall::new(()).abi_encode());
+        let metadata = TokenMetadata {
+            name: self.read_string(name_call, session),
+            symbol: self.read_string(symbol_call, session),
+            decimals,
+        };
+        session.cache_token_metadata(self.token, metadata.clone());
+
+        Ok(metadata)
+    }
 
-        let result = evm.transact()?;
-        let (output, _, _, _) = extract_gas_output_and_logs(&result.result)?;
-        let access_list = extract_access_list(&result);
-        let output = <U256>::abi_decode(&output, false)?;
+    /// Parses an amount either in raw units (`1500000`) or, when it has a decimal point, in token
+    /// units (`1.5`), which are scaled by the decimals of the token.
+    pub fn parse_amount<DB: SimulationDatabase>(
+        &self,
+        amount: &str,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<U256> {
+        if !amount.contains('.') {
+            return amount
+                .parse::<U256>()
+                .map_err(|e| SimulationError::Config(format!("Invalid amount {amount}: {e}")));
+        }
 
-        Ok((output, access_list))
+        let metadata = self.metadata(session)?;
+        parse_units(amount, metadata.decimals)
     }
 
-    #[allow(dead_code)]
-    pub fn allowance(
+    // Empty when the call fails or returns neither a `string` nor a `bytes32`.
+    fn read_string<DB: SimulationDatabase>(
+        &self,
+        calldata: Bytes,
+        session: &mut SimulationSession<DB>,
+    ) -> String {
+        let decoded = session.call(self.token, calldata, |output| {
+            if let Ok(value) = <String>::abi_decode(output, true) {
+                return Ok(value);
+            }
+
+            let value = <B256>::abi_decode(output, false)?;
+            let length = value.iter().position(|&byte| byte == 0).unwrap_or(32);
+            Ok(String::from_utf8_lossy(&value[..length]).into_owned())
+        });
+
+        decoded.map(|outcome| outcome.decoded).unwrap_or_default()
+    }
+
+    /// Sums the amounts of the token transferred to `account` in `logs`.
+    pub fn amount_received(&self, account: Address, logs: &[Log]) -> U256 {
+        logs.iter()
+            .filter(|log| log.address == self.token)
+            .filter_map(|log| IErc20::Transfer::decode_log(log, true).ok())
+            .filter(|transfer| transfer.to == account)
+            .map(|transfer| transfer.value)
+            .fold(U256::ZERO, U256::saturating_add)
+    }
+
+    pub fn balance_of<DB: SimulationDatabase>(
+        &self,
+        account: Address,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<U256>> {
+        let calldata = Bytes::from(IErc20::balanceOfCall::new((account,)).abi_encode());
+
+        session.call(self.token, calldata, |output| {
+            Ok(<U256>::abi_decode(output, false)?)
+        })
+    }
+
+    pub fn allowance<DB: SimulationDatabase>(
         &self,
         owner: Address,
         spender: Address,
-        database: &mut AlloyCacheDB,
-    ) -> Result<(U256, AccessList)> {
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<U256>> {
         let calldata = Bytes::from(IErc20::allowanceCall::new((owner, spender)).abi_encode());
-        let mut evm = Evm::builder()
-            .with_db(database)
-            .modify_tx_env(|tx| {
-                tx.caller = self.caller;
-                tx.transact_to = TxKind::Call(self.token);
-                tx.data = calldata;
-                tx.value = U256::from(0);
-            })
-            .build();
-
-        let result = evm.transact()?;
-        let (output, _, _, _) = extract_gas_output_and_logs(&result.result)?;
-        let output = <U256>::abi_decode(&output, false)?;
-        let access_list = extract_access_list(&result);
 
-        Ok((output, access_list))
+        session.call(self.token, calldata, |output| {
+            Ok(<U256>::abi_decode(output, false)?)
+        })
     }
 
-    pub fn approve(
+    /// Approves `spender` like OpenZeppelin's `SafeERC20`: tokens returning nothing (e.g. USDT)
+    /// succeed, and tokens returning `false` fail with an invariant violation, in which case
+    /// nothing is committed.
+    pub fn approve<DB: SimulationDatabase>(
         &self,
         spender: Address,
         amount: U256,
-        database: &mut AlloyCacheDB,
-    ) -> Result<(bool, AccessList)> {
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<bool>> {
         let calldata = Bytes::from(IErc20::approveCall::new((spender, amount)).abi_encode());
-        let mut evm = Evm::builder()
-            .with_db(&mut *database)
-            .modify_tx_env(|tx| {
-                tx.caller = self.caller;
-                tx.transact_to = TxKind::Call(self.token);
-                tx.data = calldata.clone();
-                tx.value = U256::from(0);
-            })
-            .build();
-
-        // Checkpoint:
-        // First execution ensures that the transaction is valid and also allows us to get the
-        // storage slots that were touched. At this point it is important not to commit the
-        // changes to the database.
-        let result = evm.transact()?;
-        let (output, _, gas_used, gas_refunded) = extract_gas_output_and_logs(&result.result)?;
-        let output = <bool>::abi_decode(&output, false)?;
-
-        // FIX: These access list must be incorrectly calculated as the consume more gas than the
-        // original tx
-        let access_list = extract_access_list(&result);
-
-        drop(evm);
-
-        println!(
-            "ERC20 Approve - Gas used with no access list: {:?}",
-            gas_used
-        );
-        println!(
-            "ERC20 Approve - Gas refunded with no access list: {:?}",
-            gas_refunded
-        );
-        println!("ERC20 Approve - Output: {:?}", output);
-
-        let mut evm = Evm::builder()
-            .with_db(database)
-            .modify_tx_env(|tx| {
-                tx.caller = self.caller;
-                tx.transact_to = TxKind::Call(self.token);
-                tx.data = calldata;
-                tx.value = U256::from(0);
-                tx.access_list = access_list.0.clone();
-            })
-            .build();
-
-        // Checkpoint:
-        // We execute the same transaction but this time including the access list and committing
-        // the changes to the database. It is important to commit the changes or the swap via the
-        // router will fail as it will try to perform a `transfer_from`.
-        //
-        // TODO: We could add an extra flag to indicate whether to commit or not.
-        // TODO: We could include an extra validation so ensure that the output on the first
-        // transaction matches the output on the second transaction.
-        let result = evm.transact_commit()?;
-        let (output, _, gas_used, gas_refunded) = extract_gas_output_and_logs(&result)?;
-        let output = <bool>::abi_decode(&output, false)?;
-
-        println!("ERC20 Approve - Gas used with access list: {:?}", gas_used);
-        println!(
-            "ERC20 Approve - Gas refunded with access list: {:?}",
-            gas_refunded
-        );
-        println!("ERC20 Approve - Output: {:?}", output);
-
-        Ok((output, access_list))
+
+        session.execute(self.token, calldata, |output| {
+            self.check_success("approve", output)
+        })
     }
 
-    pub fn transfer(
+    /// Approves `spender`, resetting the allowance to zero first when the token refuses to
+    /// change an allowance that is not zero, like USDT does.
+    pub fn force_approve<DB: SimulationDatabase>(
+        &self,
+        spender: Address,
+        amount: U256,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<Vec<SimulationOutcome<bool>>> {
+        match self.approve(spender, amount, session) {
+            Ok(outcome) => Ok(vec![outcome]),
+            Err(SimulationError::Revert { .. } | SimulationError::InvariantViolation(_))
+                if !amount.is_zero() =>
+            {
+                let reset = self.approve(spender, U256::ZERO, session)?;
+                let approval = self.approve(spender, amount, session)?;
+                Ok(vec![reset, approval])
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Transfers like OpenZeppelin's `SafeERC20`, see [`Erc20::approve`].
+    pub fn transfer<DB: SimulationDatabase>(
         &self,
         destination: Address,
         amount: U256,
-        database: &mut AlloyCacheDB,
-    ) -> Result<(bool, AccessList)> {
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<bool>> {
         let calldata = Bytes::from(IErc20::transferCall::new((destination, amount)).abi_encode());
-        let mut evm = Evm::builder()
-            .with_db(&mut *database)
-            .modify_tx_env(|tx| {
-                tx.caller = self.caller;
-                tx.transact_to = TxKind::Call(self.token);
-                tx.data = calldata.clone();
-                tx.value = U256::from(0);
-            })
-            .build();
-
-        // Checkpoint:
-        // First execution ensures that the transaction is valid and also allows us to get the
-        // storage slots that were touched. At this point it is important not to commit the
-        // changes to the database.
-        let result = evm.transact()?;
-        let (output, _, gas_used, gas_refunded) = extract_gas_output_and_logs(&result.result)?;
-        let output = <bool>::abi_decode(&output, false)?;
-
-        // FIX: These access list must be incorrectly calculated as the consume more gas than the
-        // original tx
-        let access_list = extract_access_list(&result);
-
-        drop(evm);
-
-        println!(
-            "ERC20 Transfer - Gas used with no access list: {:?}",
-            gas_used
-        );
-        println!(
-            "ERC20 Transfer - Gas refunded with no access list: {:?}",
-            gas_refunded
-        );
-        println!("ERC20 Transfer - Output: {:?}", output);
-
-        let mut evm = Evm::builder()
-            .with_db(database)
-            .modify_tx_env(|tx| {
-                tx.caller = self.caller;
-                tx.transact_to = TxKind::Call(self.token);
-                tx.data = calldata;
-                tx.value = U256::from(0);
-                tx.access_list = access_list.0.clone();
-            })
-            .build();
-
-        // Checkpoint:
-        // We execute the same transaction but this time including the access list and committing
-        // the changes to the database. It is important to commit the changes or the swap via the
-        // router will fail as it will try to perform a `transfer_from`.
-        //
-        // TODO: We could add an extra flag to indicate whether to commit or not.
-        // TODO: We could include an extra validation so ensure that the output on the first
-        // transaction matches the output on the second transaction.
-        let result = evm.transact_commit()?;
-        let (output, _, gas_used, gas_refunded) = extract_gas_output_and_logs(&result)?;
-        let output = <bool>::abi_decode(&output, false)?;
-
-        println!("ERC20 Transfer - Gas used with access list: {:?}", gas_used);
-        println!(
-            "ERC20 Transfer - Gas refunded with access list: {:?}",
-            gas_refunded
-        );
-        println!("ERC20 Transfer - Output: {:?}", output);
-
-        Ok((output, access_list))
+
+        session.execute(self.token, calldata, |output| {
+            self.check_success("transfer", output)
+        })
+    }
+
+    // No return data counts as a success, as for the tokens predating the final ERC20 standard.
+    fn check_success(&self, operation: &str, output: &[u8]) -> Result<bool> {
+        if output.is_empty() {
+            return Ok(true);
+        }
+
+        if !<bool>::abi_decode(output, false)? {
+            return Err(SimulationError::InvariantViolation(format!(
+                "{} returned false on {operation}",
+                self.token
+            )));
+        }
+
+        Ok(true)
     }
 
-    pub fn set_balance(
+    pub fn set_balance<DB: SimulationDatabase>(
         &self,
         account: Address,
         amount: U256,
-        database: &mut AlloyCacheDB,
+        session: &mut SimulationSession<DB>,
     ) -> Result<()> {
-        let slot = self.get_balance_slot(account, database)?;
-        let _ = database.insert_account_storage(self.token, slot, amount);
-
-        Ok(())
+        let mapping = self.balance_mapping(account, session)?;
+        mapping.write(&[account.into_word()], amount, session.database())
     }
 
-    fn get_balance_slot(&self, account: Address, database: &mut AlloyCacheDB) -> Result<U256> {
-        let (_, touched_storage) = self.balance_of(account, database)?;
-        let touched_storage = touched_storage
-            .iter()
-            .find(|&storage| storage.address == self.token)
-            .unwrap();
+    /// Overrides the allowance given by `owner` to `spender` in the token storage, which saves
+    /// executing an approval before the calls spending the tokens.
+    pub fn set_allowance<DB: SimulationDatabase>(
+        &self,
+        owner: Address,
+        spender: Address,
+        amount: U256,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<()> {
+        let mapping = self.allowance_mapping(owner, spender, session)?;
+        mapping.write(
+            &[owner.into_word(), spender.into_word()],
+            amount,
+            session.database(),
+        )
+    }
 
-        for i in 0..50 {
-            let slot = keccak256((account, i).abi_encode());
+    /// Discovers where the token stores the allowance given by `owner` to `spender`, a mapping
+    /// nested in another one. See [`find_mapping`].
+    fn allowance_mapping<DB: SimulationDatabase>(
+        &self,
+        owner: Address,
+        spender: Address,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<StorageMapping> {
+        let calldata = Bytes::from(IErc20::allowanceCall::new((owner, spender)).abi_encode());
+        let keys = [owner.into_word(), spender.into_word()];
+
+        find_mapping(session, self.token, calldata, &keys)?.ok_or_else(|| {
+            SimulationError::InvariantViolation(format!(
+                "The allowance slot of {owner} for {spender} could not be found in {}",
+                self.token
+            ))
+        })
+    }
 
-            if touched_storage.storage_keys.iter().any(|key| key == &slot) {
-                return Ok(U256::from_be_bytes(slot.into()));
-            };
-        }
+    /// Discovers where the token stores the balance of `account`. See [`find_mapping`].
+    fn balance_mapping<DB: SimulationDatabase>(
+        &self,
+        account: Address,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<StorageMapping> {
+        let calldata = Bytes::from(IErc20::balanceOfCall::new((account,)).abi_encode());
 
-        Err(anyhow!("Storage slot not found"))
+        find_mapping(session, self.token, calldata, &[account.into_word()])?.ok_or_else(|| {
+            SimulationError::InvariantViolation(format!(
+                "The balance slot of {account} could not be found in {}",
+                self.token
+            ))
+        })
     }
 }
@@ -0,0 +1,73 @@
+use revm::primitives::{Bytes, EVMError, HaltReason};
+use thiserror::Error;
+
+use super::revert::{describe_halt, RevertReason};
+
+pub type Result<T, E = SimulationError> = std::result::Result<T, E>;
+
+/// Everything that can go wrong while running a simulation.
+#[derive(Debug, Error)]
+pub enum SimulationError {
+    /// The node could not be reached or answered with an error, including the failures to fetch
+    /// the state of the fork.
+    #[error("RPC failure: {0}")]
+    Rpc(String),
+
+    #[error("Reverted: {reason}")]
+    Revert { reason: RevertReason, output: Bytes },
+
+    #[error("Halted: {} (gas used: {gas_used})", describe_halt(reason))]
+    Halt { reason: HaltReason, gas_used: u64 },
+
+    /// The output of a call or a log could not be decoded.
+    #[error("Decoding failed: {0}")]
+    Decoding(String),
+
+    /// The simulation executed, but its results are not the expected ones, e.g. the balances
+    /// after a swap do not match the amounts swapped.
+    #[error("Invariant violated: {0}")]
+    InvariantViolation(String),
+
+    /// The arguments or the configuration of the simulation are not valid.
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    /// The EVM refused to execute the transaction, e.g. because of an invalid nonce.
+    #[error("EVM error: {0}")]
+    Evm(String),
+}
+
+impl SimulationError {
+    pub fn rpc(error: impl std::fmt::Display) -> Self {
+        Self::Rpc(error.to_string())
+    }
+
+    /// Exit code of the CLI for this error. `1` is left for unexpected failures (panics) and
+    /// `2` is shared with the usage errors reported by clap.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Config(_) => 2,
+            Self::Rpc(_) => 3,
+            Self::Revert { .. } => 4,
+            Self::Halt { .. } => 5,
+            Self::Decoding(_) => 6,
+            Self::InvariantViolation(_) => 7,
+            Self::Evm(_) => 8,
+        }
+    }
+}
+
+impl From<alloy_sol_types::Error> for SimulationError {
+    fn from(error: alloy_sol_types::Error) -> Self {
+        Self::Decoding(error.to_string())
+    }
+}
+
+impl<DBError: std::error::Error> From<EVMError<DBError>> for SimulationError {
+    fn from(error: EVMError<DBError>) -> Self {
+        match error {
+            EVMError::Database(error) => Self::rpc(error),
+            error => Self::Evm(error.to_string()),
+        }
+    }
+}
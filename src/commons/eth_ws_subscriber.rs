@@ -1,4 +1,3 @@
-use anyhow::Result;
 use futures::{SinkExt, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -6,14 +5,11 @@ use std::{future, pin::Pin};
 use tokio_tungstenite::connect_async;
 use tungstenite::Message;
 
-pub type Subscription<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
+use super::errors::{Result, SimulationError};
 
-#[derive(Debug, Deserialize)]
-enum EthWsResponse<T> {
-    Connected,
-    Message(T),
-    Empty(),
-}
+/// Notifications of a subscription. A message that cannot be read or decoded, or the connection
+/// closing, is yielded as an error.
+pub type Subscription<T> = Pin<Box<dyn Stream<Item = Result<T>> + Send>>;
 
 #[derive(Default, Serialize)]
 pub struct EthWsSubscriptionRequest {
@@ -43,6 +39,7 @@ impl EthWsSubscriptionRequest {
     }
 }
 
+/// Subscribes through `eth_subscribe`, failing when the node refuses the subscription.
 pub async fn subscribe<T>(
     url: String,
     subscription_request: EthWsSubscriptionRequest,
@@ -50,49 +47,75 @@ pub async fn subscribe<T>(
 where
     T: for<'a> Deserialize<'a> + Serialize + Send + 'static,
 {
-    let (stream, _) = connect_async(url).await.expect("Failed to connect");
-    let (mut writer, reader) = stream.split();
-
-    let reader = reader
-        .map(|result| match result {
-            Ok(message) => match message {
-                Message::Text(text) => handle_message(text),
-                Message::Close(text) => {
-                    println!("Connection closed. Reason: {:?}", text);
-                    None
-                }
-                Message::Ping(_) | Message::Pong(_) => None,
-                _ => panic!("Unexpected message: {:?}", message),
-            },
-            Err(e) => {
-                println!("Error reading stream. Reason: {:?}", e);
-                None
-            }
-        })
-        .filter_map(future::ready);
+    let (stream, _) = connect_async(url).await.map_err(SimulationError::rpc)?;
+    let (mut writer, mut reader) = stream.split();
 
+    let request_id = subscription_request.id;
+    let subscription_request =
+        serde_json::to_string(&subscription_request).map_err(SimulationError::rpc)?;
     writer
-        .send(Message::Text(serde_json::to_string(&subscription_request).unwrap()).clone())
-        .await?;
+        .send(Message::Text(subscription_request))
+        .await
+        .map_err(SimulationError::rpc)?;
+
+    // The node answers the request before sending any notification.
+    loop {
+        let message = reader
+            .next()
+            .await
+            .ok_or_else(|| SimulationError::Rpc("The connection closed on subscribing".to_owned()))?
+            .map_err(SimulationError::rpc)?;
+        let Message::Text(text) = message else {
+            continue;
+        };
+
+        let response: Value = serde_json::from_str(&text)
+            .map_err(|e| SimulationError::Decoding(format!("Invalid message {text}: {e}")))?;
+        if response["id"] != request_id {
+            continue;
+        }
+        if let Some(error) = response.get("error") {
+            return Err(SimulationError::Rpc(format!(
+                "The subscription was refused: {error}"
+            )));
+        }
+        break;
+    }
+
+    let reader = reader.filter_map(|result| {
+        future::ready(match result {
+            Ok(Message::Text(text)) => handle_message(&text),
+            Ok(Message::Close(frame)) => Some(Err(SimulationError::Rpc(format!(
+                "The connection was closed: {frame:?}"
+            )))),
+            Ok(Message::Ping(_) | Message::Pong(_)) => None,
+            Ok(message) => Some(Err(SimulationError::Decoding(format!(
+                "Unexpected message {message:?}"
+            )))),
+            Err(e) => Some(Err(SimulationError::rpc(e))),
+        })
+    });
 
     Ok(Box::pin(reader))
 }
 
-fn handle_message<T>(json_str: String) -> Option<T>
+// Decodes the result of a notification. Other messages are skipped.
+fn handle_message<T>(text: &str) -> Option<Result<T>>
 where
     T: for<'a> Deserialize<'a> + Serialize + Send + 'static,
 {
-    let value: Value = serde_json::from_str(&json_str).unwrap();
-
-    let value = value.as_object().unwrap();
-    if value.contains_key("params") && value.contains_key("method") {
-        let params = value["params"].as_object().unwrap();
-        if params.contains_key("result") {
-            let result = serde_json::from_value::<T>(params["result"].clone()).unwrap();
-
-            return Some(result);
+    let value: Value = match serde_json::from_str(text) {
+        Ok(value) => value,
+        Err(e) => {
+            return Some(Err(SimulationError::Decoding(format!(
+                "Invalid message {text}: {e}"
+            ))))
         }
-    }
+    };
 
-    None
+    let result = value.get("params")?.get("result")?;
+    Some(
+        serde_json::from_value::<T>(result.clone())
+            .map_err(|e| SimulationError::Decoding(format!("Invalid notification {text}: {e}"))),
+    )
 }
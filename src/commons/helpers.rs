@@ -1,22 +1,41 @@
-use alloy_provider::{network::Ethereum, RootProvider};
-use alloy_sol_types::decode_revert_reason;
+use alloy_eips::BlockId;
+use alloy_provider::{network::Ethereum, Provider, RootProvider};
+use alloy_rpc_types::{Block, BlockTransactionsKind};
 use alloy_transport_http::Http;
-use anyhow::{anyhow, Result};
 use reqwest::Client;
 use revm::db::{AlloyDB, CacheDB};
 use revm::primitives::{
-    AccessList, AccessListItem, Address, Bytes, ExecutionResult, Log, Output, ResultAndState, B256,
-    U256,
+    Account, Address, Bytecode, Bytes, EvmStorageSlot, ExecutionResult, HashMap, Log, Output, U256,
 };
+use revm::{Database, DatabaseCommit};
+
+use super::erc20::Erc20;
+use super::errors::{Result, SimulationError};
+use super::revert::decode_revert;
+use super::session::SimulationSession;
 
 pub type AlloyCacheDB<'a> =
     CacheDB<AlloyDB<Http<Client>, Ethereum, &'a RootProvider<Http<Client>>>>;
 
-pub fn get_revert_message(revert_message: &Bytes) -> String {
-    match decode_revert_reason(revert_message) {
-        None => revert_message.to_string(),
-        Some(message) => message,
-    }
+/// Any database the simulations can run against: an RPC backed fork, an in-memory database or
+/// an on-disk snapshot, as long as changes can be committed to it.
+pub trait SimulationDatabase:
+    Database<Error: std::error::Error + Send + Sync + 'static> + DatabaseCommit
+{
+}
+
+impl<T> SimulationDatabase for T where
+    T: Database<Error: std::error::Error + Send + Sync + 'static> + DatabaseCommit
+{
+}
+
+/// Fetches the block identified by a number, hash or tag (`latest`, `safe`, ...).
+pub async fn fetch_block(client: &RootProvider<Http<Client>>, block_id: BlockId) -> Result<Block> {
+    client
+        .get_block(block_id, BlockTransactionsKind::Hashes)
+        .await
+        .map_err(SimulationError::rpc)?
+        .ok_or_else(|| SimulationError::Config(format!("Block {block_id} not found")))
 }
 
 pub fn extract_gas_output_and_logs(
@@ -30,32 +49,87 @@ pub fn extract_gas_output_and_logs(
             logs,
             ..
         } => Ok((value.clone(), logs.clone(), *gas_used, *gas_refunded)),
-        ExecutionResult::Revert { output, .. } => {
-            Err(anyhow!("Reverted: {:?}", get_revert_message(output)))
-        }
-        result => Err(anyhow!("Execution failed: {result:?}")),
+        ExecutionResult::Revert { output, .. } => Err(SimulationError::Revert {
+            reason: decode_revert(output),
+            output: output.clone(),
+        }),
+        ExecutionResult::Halt { reason, gas_used } => Err(SimulationError::Halt {
+            reason: *reason,
+            gas_used: *gas_used,
+        }),
+        ExecutionResult::Success { .. } => Err(SimulationError::Decoding(
+            "Expected the output of a call but found the output of a contract creation".to_owned(),
+        )),
     }
 }
 
-pub fn extract_access_list(result: &ResultAndState) -> AccessList {
-    let storages = result
-        .state
-        .iter()
-        .filter(|(_, account)| !account.storage.is_empty())
-        .map(|(&address, account)| AccessListItem {
-            address,
-            storage_keys: account
-                .storage
-                .keys()
-                .map(|k| B256::from(k.to_be_bytes()))
-                .collect(),
-        })
-        .collect::<Vec<_>>();
+pub fn set_eth_balance<DB: SimulationDatabase>(
+    account: Address,
+    amount: U256,
+    database: &mut DB,
+) -> Result<()> {
+    override_account(account, database, |account| account.info.balance = amount)
+}
+
+/// Gives the caller of the session `amount` of `token`, and as much wei for the gas, so a swap can
+/// be simulated from an account that does not hold the tokens.
+///
+/// This is a convenience only, to be used with extreme caution: the balances are written in the
+/// state, so the simulation no longer matches the real state of the blockchain.
+pub fn fund_caller<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    token: &Erc20,
+    amount: U256,
+) -> Result<()> {
+    let caller = session.caller();
+    set_eth_balance(caller, amount, session.database())?;
+    token.set_balance(caller, amount, session)
+}
 
-    AccessList::from(storages)
+pub fn set_code<DB: SimulationDatabase>(
+    account: Address,
+    code: Bytecode,
+    database: &mut DB,
+) -> Result<()> {
+    override_account(account, database, |account| {
+        account.info.code_hash = code.hash_slow();
+        account.info.code = Some(code);
+    })
 }
 
-pub fn set_eth_balance(account: Address, amount: U256, database: &mut AlloyCacheDB) {
-    let account = database.load_account(account).unwrap();
-    account.info.balance = amount;
+pub fn set_storage<DB: SimulationDatabase>(
+    account: Address,
+    slot: U256,
+    value: U256,
+    database: &mut DB,
+) -> Result<()> {
+    let original_value = database
+        .storage(account, slot)
+        .map_err(SimulationError::rpc)?;
+
+    override_account(account, database, |account| {
+        account
+            .storage
+            .insert(slot, EvmStorageSlot::new_changed(original_value, value));
+    })
+}
+
+// State overrides are committed as regular state changes, which is the only write access
+// exposed by `DatabaseCommit` and therefore works for any database.
+fn override_account<DB: SimulationDatabase>(
+    address: Address,
+    database: &mut DB,
+    modify: impl FnOnce(&mut Account),
+) -> Result<()> {
+    let info = database
+        .basic(address)
+        .map_err(SimulationError::rpc)?
+        .unwrap_or_default();
+    let mut account = Account::from(info);
+    account.mark_touch();
+    modify(&mut account);
+
+    database.commit(HashMap::from_iter([(address, account)]));
+
+    Ok(())
 }
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use alloy_signer_local::PrivateKeySigner;
+use revm::primitives::{keccak256, Address};
+
+use super::errors::{Result, SimulationError};
+
+/// The account the simulated transactions are sent from.
+///
+/// When an identity is given, simulations run with the real nonce and balances of the account
+/// instead of funding a made up caller.
+#[derive(Debug, Clone)]
+pub enum Identity {
+    /// An account we hold the key of, hence able to sign the transactions.
+    Signer(PrivateKeySigner),
+    /// An account we can only simulate as.
+    Address(Address),
+}
+
+impl Identity {
+    pub fn from_private_key(private_key: &str) -> Result<Self> {
+        let signer = private_key
+            .parse::<PrivateKeySigner>()
+            .map_err(|e| SimulationError::Config(format!("Invalid private key: {e}")))?;
+
+        Ok(Self::Signer(signer))
+    }
+
+    /// Decrypts the JSON keystore (the format used by geth, foundry, ...) at `path`.
+    pub fn from_keystore(path: &Path, password: &str) -> Result<Self> {
+        let signer = PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| {
+            SimulationError::Config(format!(
+                "Cannot decrypt the keystore {}: {e}",
+                path.display()
+            ))
+        })?;
+
+        Ok(Self::Signer(signer))
+    }
+
+    pub fn address(&self) -> Address {
+        match self {
+            Self::Signer(signer) => compute_address(signer),
+            Self::Address(address) => *address,
+        }
+    }
+
+    pub fn into_signer(self) -> Option<PrivateKeySigner> {
+        match self {
+            Self::Signer(signer) => Some(signer),
+            Self::Address(_) => None,
+        }
+    }
+
+    pub fn signer(&self) -> Option<&PrivateKeySigner> {
+        match self {
+            Self::Signer(signer) => Some(signer),
+            Self::Address(_) => None,
+        }
+    }
+}
+
+/// Derives the address of a private key: the last 20 bytes of the hash of its uncompressed public
+/// key, without the `0x04` prefix.
+pub fn compute_address(signer: &PrivateKeySigner) -> Address {
+    let public_key = signer.credential().verifying_key().to_encoded_point(false);
+
+    let public_key_bytes = &public_key.as_bytes()[1..];
+
+    let public_key_hash = keccak256(public_key_bytes);
+    let address = &public_key_hash[12..];
+
+    Address::from_slice(address)
+}
@@ -1,3 +1,40 @@
-pub mod erc20;
-pub mod eth_ws_subscriber;
-pub mod helpers;
+//! Building blocks of the simulations. The modules are internal, everything reachable from the
+//! public API is re-exported here.
+
+pub(crate) mod access_list;
+pub(crate) mod builder;
+pub(crate) mod erc20;
+pub(crate) mod errors;
+pub(crate) mod eth_ws_subscriber;
+pub(crate) mod helpers;
+pub(crate) mod identity;
+pub(crate) mod monitor;
+pub(crate) mod registry;
+pub(crate) mod results;
+pub(crate) mod revert;
+pub(crate) mod session;
+pub(crate) mod storage;
+pub(crate) mod transaction;
+pub(crate) mod units;
+
+pub use access_list::AccessListInspector;
+pub use builder::{submit_bundle, BuilderClient, BundleSubmission, FLASHBOTS_SIGNATURE_HEADER};
+pub use erc20::TokenMetadata;
+pub use errors::{Result, SimulationError};
+pub use eth_ws_subscriber::{subscribe, EthWsSubscriptionRequest, Subscription};
+pub use helpers::{
+    extract_gas_output_and_logs, fetch_block, fund_caller, set_code, set_eth_balance, set_storage,
+    AlloyCacheDB, SimulationDatabase,
+};
+pub use identity::Identity;
+pub use monitor::{monitor_inclusion, IncludedTransaction, InclusionReport};
+pub use registry::{ChainConfig, FactoryConfig, Registry};
+pub use results::{
+    AccountDiff, ExecutionStatus, GasReport, SimulationOutcome, SimulationStep, StorageChange,
+    TransferTax,
+};
+pub use revert::{decode_revert, describe_halt, RevertReason};
+pub use session::spec_id_at;
+pub use storage::{HashOrder, StorageMapping};
+pub use transaction::{sign_steps, SignedTransaction, TransactionSettings};
+pub use units::{format_units, parse_units};
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::Header;
+use alloy_transport_http::Http;
+use futures::StreamExt;
+use reqwest::Client;
+use revm::primitives::{B256, U256};
+use serde::Serialize;
+
+use super::erc20::{Erc20, TokenMetadata};
+use super::errors::{Result, SimulationError};
+use super::eth_ws_subscriber::Subscription;
+use super::results::{format_amount, SwapResult};
+
+/// A submitted transaction found in a block, compared to its simulation.
+#[derive(Debug, Clone, Serialize)]
+pub struct IncludedTransaction {
+    pub name: String,
+    pub hash: B256,
+    pub block_number: u64,
+    pub success: bool,
+    pub effective_gas_price: u128,
+    pub gas_used: u64,
+    pub simulated_gas_used: u64,
+}
+
+/// What happened to the transactions of a swap once submitted.
+#[derive(Debug, Clone, Serialize)]
+pub struct InclusionReport {
+    pub included: Vec<IncludedTransaction>,
+    /// The transactions still not included when giving up.
+    pub missing: Vec<B256>,
+    pub blocks_watched: u64,
+    /// Amount of token out actually received, only known once every transaction is included.
+    pub amount_out: Option<U256>,
+    pub simulated_amount_out: U256,
+    /// Metadata of the token out, only used to print the amounts.
+    #[serde(skip)]
+    pub token_out_metadata: Option<TokenMetadata>,
+}
+
+impl fmt::Display for InclusionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for transaction in &self.included {
+            let name = &transaction.name;
+
+            writeln!(
+                f,
+                "{name} - Included in block {} ({})",
+                transaction.block_number,
+                if transaction.success {
+                    "success"
+                } else {
+                    "reverted"
+                }
+            )?;
+            writeln!(
+                f,
+                "{name} - Effective gas price: {}",
+                transaction.effective_gas_price
+            )?;
+            writeln!(
+                f,
+                "{name} - Gas used: {} (simulated: {})",
+                transaction.gas_used, transaction.simulated_gas_used
+            )?;
+        }
+        for hash in &self.missing {
+            writeln!(
+                f,
+                "Transaction {hash} not included after {} blocks",
+                self.blocks_watched
+            )?;
+        }
+        let token_out = self.token_out_metadata.as_ref();
+        let simulated_amount_out = format_amount(self.simulated_amount_out, token_out);
+        match self.amount_out {
+            Some(amount_out) => write!(
+                f,
+                "Amount out: {} (simulated: {simulated_amount_out})",
+                format_amount(amount_out, token_out)
+            ),
+            None => write!(f, "Amount out: unknown (simulated: {simulated_amount_out})"),
+        }
+    }
+}
+
+/// Watches the new blocks announced by `new_heads` until every transaction of the swap is
+/// included, or gives up after `max_blocks` blocks.
+///
+/// The receipts are fetched at each new block, which reports how the transactions executed on
+/// chain, along with the amount of token out the sender received from them.
+pub async fn monitor_inclusion(
+    client: &RootProvider<Http<Client>>,
+    mut new_heads: Subscription<Header>,
+    swap: &SwapResult,
+    max_blocks: u64,
+) -> Result<InclusionReport> {
+    let Some(sender) = swap
+        .transactions
+        .first()
+        .map(|transaction| transaction.from)
+    else {
+        return Err(SimulationError::Config(
+            "There are no transactions to monitor".to_owned(),
+        ));
+    };
+
+    let mut pending = swap.transactions.iter().collect::<Vec<_>>();
+    let mut included = vec![];
+    let mut logs = vec![];
+    let mut blocks_watched = 0;
+
+    while !pending.is_empty() && blocks_watched < max_blocks {
+        if new_heads.next().await.transpose()?.is_none() {
+            return Err(SimulationError::Rpc(
+                "The subscription to new blocks ended".to_owned(),
+            ));
+        }
+        blocks_watched += 1;
+
+        let mut still_pending = vec![];
+        for transaction in pending {
+            let receipt = client
+                .get_transaction_receipt(transaction.hash)
+                .await
+                .map_err(SimulationError::rpc)?;
+
+            match receipt {
+                Some(receipt) => {
+                    logs.extend(receipt.inner.logs().iter().map(|log| log.inner.clone()));
+
+                    included.push(IncludedTransaction {
+                        name: transaction.name.clone(),
+                        hash: transaction.hash,
+                        block_number: receipt.block_number.unwrap_or_default(),
+                        success: receipt.status(),
+                        effective_gas_price: receipt.effective_gas_price,
+                        gas_used: receipt.gas_used.try_into().unwrap_or(u64::MAX),
+                        simulated_gas_used: transaction.simulated_gas_used,
+                    });
+                }
+                None => still_pending.push(transaction),
+            }
+        }
+        pending = still_pending;
+    }
+
+    let amount_out = pending
+        .is_empty()
+        .then(|| Erc20::new(swap.token_out).amount_received(sender, &logs));
+
+    Ok(InclusionReport {
+        included,
+        missing: pending.iter().map(|transaction| transaction.hash).collect(),
+        blocks_watched,
+        amount_out,
+        simulated_amount_out: swap.amount_out,
+        token_out_metadata: swap.token_out_metadata.clone(),
+    })
+}
@@ -0,0 +1,215 @@
+use std::path::Path;
+
+use revm::primitives::{address, b256, Address, B256};
+use serde::{Deserialize, Serialize};
+
+use super::errors::{Result, SimulationError};
+use crate::uniswap_v2::v2_math::SwapFee;
+
+/// A Uniswap V2 (or V2 fork) or Uniswap V3 factory, along with the hash of the init code of the
+/// pools it deploys, which allows computing the pool addresses offline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FactoryConfig {
+    pub name: String,
+    pub address: Address,
+    pub init_code_hash: B256,
+    /// Fee taken by the V2 pairs, 0.3% unless given. V3 pools each have their own fee.
+    #[serde(default)]
+    pub fee: SwapFee,
+}
+
+/// Addresses of the contracts the simulations interact with on a given chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub name: String,
+    pub weth: Address,
+    pub multicall3: Address,
+    pub uniswap_v2_router: Address,
+    /// Address where the Uniswap V3 simulator contract is deployed in the fork. The contract
+    /// only lives in the simulations, hence any address without code works.
+    pub uniswap_v3_simulator: Address,
+    #[serde(default)]
+    pub uniswap_v2_factories: Vec<FactoryConfig>,
+    #[serde(default)]
+    pub uniswap_v3_factories: Vec<FactoryConfig>,
+    /// Endpoints accepting `eth_sendBundle`, used when submitting the swap transactions.
+    #[serde(default)]
+    pub builders: Vec<String>,
+}
+
+/// The addresses of every supported chain, keyed by chain id.
+///
+/// The registry comes with the Ethereum mainnet addresses built-in, and can be extended or
+/// overridden with a TOML file such as:
+///
+/// ```toml
+/// caller = "0xFF3cF7b8582571095A2B05268A4E1BafBDAD060D"
+///
+/// [[chains]]
+/// chain_id = 11155111
+/// name = "sepolia"
+/// weth = "0xfFf9976782d46CC05630D1f6eBAb18b2324d6B14"
+/// multicall3 = "0xcA11bde05977b3631167028862bE2a173976CA11"
+/// uniswap_v2_router = "0xeE567Fe1712Faf6149d80dA1E6934E354124CfE3"
+/// uniswap_v3_simulator = "0x1100000000000000000000000000000000000011"
+///
+/// [[chains.uniswap_v2_factories]]
+/// name = "uniswap"
+/// address = "0xF62c03E08ada871A0bEb309762E260a7a6a880E6"
+/// init_code_hash = "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+/// fee = { numerator = 3, denominator = 1000 }
+/// ```
+///
+/// Chains can also list the `builders` endpoints the bundles are submitted to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Registry {
+    /// Account used to send the simulated transactions when no identity is provided.
+    #[serde(default = "default_caller")]
+    pub caller: Address,
+    #[serde(default)]
+    pub chains: Vec<ChainConfig>,
+}
+
+fn default_caller() -> Address {
+    address!("FF3cF7b8582571095A2B05268A4E1BafBDAD060D")
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            caller: default_caller(),
+            chains: vec![mainnet()],
+        }
+    }
+}
+
+impl Registry {
+    /// Parses a TOML configuration and merges it on top of the built-in defaults. Chains already
+    /// known are replaced by the ones in the configuration.
+    pub fn from_toml(config: &str) -> Result<Self> {
+        let config: Registry = toml::from_str(config)
+            .map_err(|e| SimulationError::Config(format!("Invalid registry config: {e}")))?;
+
+        let mut registry = Self {
+            caller: config.caller,
+            ..Self::default()
+        };
+        for chain in config.chains {
+            registry
+                .chains
+                .retain(|known| known.chain_id != chain.chain_id);
+            registry.chains.push(chain);
+        }
+
+        Ok(registry)
+    }
+
+    /// Loads the configuration file at `path`, or the built-in defaults when there is none.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => {
+                let config = std::fs::read_to_string(path).map_err(|e| {
+                    SimulationError::Config(format!("Cannot read {}: {e}", path.display()))
+                })?;
+                Self::from_toml(&config)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn chain(&self, chain_id: u64) -> Result<&ChainConfig> {
+        self.chains
+            .iter()
+            .find(|chain| chain.chain_id == chain_id)
+            .ok_or_else(|| {
+                SimulationError::Config(format!(
+                    "Chain {chain_id} is not in the registry, add it to the config file"
+                ))
+            })
+    }
+
+    /// Finds a chain either by id (`1`) or by name (`mainnet`).
+    pub fn find_chain(&self, chain: &str) -> Result<&ChainConfig> {
+        match chain.parse::<u64>() {
+            Ok(chain_id) => self.chain(chain_id),
+            Err(_) => self
+                .chains
+                .iter()
+                .find(|known| known.name.eq_ignore_ascii_case(chain))
+                .ok_or_else(|| {
+                    SimulationError::Config(format!(
+                        "Chain {chain} is not in the registry, add it to the config file"
+                    ))
+                }),
+        }
+    }
+}
+
+impl ChainConfig {
+    /// Finds a Uniswap V2 (or fork) factory by name (`uniswap`, `sushiswap`, ...).
+    pub fn uniswap_v2_factory(&self, name: &str) -> Result<&FactoryConfig> {
+        self.uniswap_v2_factories
+            .iter()
+            .find(|factory| factory.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                SimulationError::Config(format!(
+                    "No Uniswap V2 factory named {name} on {}, add it to the config file",
+                    self.name
+                ))
+            })
+    }
+}
+
+fn mainnet() -> ChainConfig {
+    ChainConfig {
+        chain_id: 1,
+        name: "mainnet".to_owned(),
+        weth: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+        multicall3: address!("cA11bde05977b3631167028862bE2a173976CA11"),
+        uniswap_v2_router: address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D"),
+        uniswap_v3_simulator: address!("1100000000000000000000000000000000000011"),
+        uniswap_v2_factories: vec![
+            FactoryConfig {
+                name: "uniswap".to_owned(),
+                address: address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
+                init_code_hash: b256!(
+                    "96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+                ),
+                fee: SwapFee::default(),
+            },
+            FactoryConfig {
+                name: "sushiswap".to_owned(),
+                address: address!("C0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac"),
+                init_code_hash: b256!(
+                    "e18a34eb0e04b04f7a0ac29a6e80748dca96319b42c520b9a3ef4e8d21b3ea3c"
+                ),
+                fee: SwapFee::default(),
+            },
+            FactoryConfig {
+                name: "pancakeswap".to_owned(),
+                address: address!("1097053Fd2ea711dad45caCcc45EfF7548fCB362"),
+                init_code_hash: b256!(
+                    "57224589c67f3f30a6b0d7a1b54cf3153ab84563bc609ef41dfb34f8b2974d2d"
+                ),
+                fee: SwapFee {
+                    numerator: 25,
+                    denominator: 10000,
+                },
+            },
+        ],
+        uniswap_v3_factories: vec![FactoryConfig {
+            name: "uniswap".to_owned(),
+            address: address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+            init_code_hash: b256!(
+                "e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54"
+            ),
+            fee: SwapFee::default(),
+        }],
+        builders: vec![
+            "https://relay.flashbots.net".to_owned(),
+            "https://rpc.beaverbuild.org".to_owned(),
+            "https://rpc.titanbuilder.xyz".to_owned(),
+        ],
+    }
+}
@@ -0,0 +1,387 @@
+use revm::primitives::{
+    AccessList, Address, Bytes, EvmState, ExecutionResult, HaltReason, Log, ResultAndState, U256,
+};
+use serde::Serialize;
+use std::fmt;
+
+use super::builder::BundleSubmission;
+use super::erc20::TokenMetadata;
+use super::errors::Result;
+use super::helpers::extract_gas_output_and_logs;
+use super::monitor::InclusionReport;
+use super::revert::{decode_revert, describe_halt, RevertReason};
+use super::transaction::SignedTransaction;
+
+/// How an execution ended, with the decoded reason when it reverted or halted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Success,
+    Revert(RevertReason),
+    Halt(HaltReason),
+}
+
+impl From<&ExecutionResult> for ExecutionStatus {
+    fn from(result: &ExecutionResult) -> Self {
+        match result {
+            ExecutionResult::Success { .. } => Self::Success,
+            ExecutionResult::Revert { output, .. } => Self::Revert(decode_revert(output)),
+            ExecutionResult::Halt { reason, .. } => Self::Halt(*reason),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Success => write!(f, "Success"),
+            Self::Revert(reason) => write!(f, "Reverted: {reason}"),
+            Self::Halt(reason) => write!(f, "Halted: {}", describe_halt(reason)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct GasReport {
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageChange {
+    pub slot: U256,
+    pub original_value: U256,
+    pub present_value: U256,
+}
+
+/// Post-execution state of an account touched by a call, along with the storage slots it
+/// changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountDiff {
+    pub address: Address,
+    pub balance: U256,
+    pub nonce: u64,
+    pub storage: Vec<StorageChange>,
+}
+
+pub fn extract_state_diff(state: &EvmState) -> Vec<AccountDiff> {
+    let mut diff = state
+        .iter()
+        .filter(|(_, account)| account.is_touched())
+        .map(|(&address, account)| {
+            let mut storage = account
+                .changed_storage_slots()
+                .map(|(&slot, value)| StorageChange {
+                    slot,
+                    original_value: value.original_value(),
+                    present_value: value.present_value(),
+                })
+                .collect::<Vec<_>>();
+            storage.sort_by_key(|change| change.slot);
+
+            AccountDiff {
+                address,
+                balance: account.info.balance,
+                nonce: account.info.nonce,
+                storage,
+            }
+        })
+        .collect::<Vec<_>>();
+    diff.sort_by_key(|account| account.address);
+
+    diff
+}
+
+/// Everything known about a simulated contract call, including the call itself so it can be
+/// turned into a transaction.
+///
+/// `gas` belongs to the first execution, which is used to generate the access list, while
+/// `gas_with_access_list` is only present when the call was executed a second time including it.
+/// `access_list_gas_saved` is only present when the access list was attached to the committed
+/// execution, which only happens when it reduces the gas used.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationOutcome<T> {
+    pub to: Address,
+    pub input: Bytes,
+    pub status: ExecutionStatus,
+    pub output: Bytes,
+    pub decoded: T,
+    pub gas: GasReport,
+    pub gas_with_access_list: Option<GasReport>,
+    pub logs: Vec<Log>,
+    pub access_list: AccessList,
+    pub access_list_gas_saved: Option<u64>,
+    pub state_diff: Vec<AccountDiff>,
+}
+
+impl<T> SimulationOutcome<T> {
+    /// Builds the outcome of a successful execution, failing when the call reverted or halted.
+    pub fn from_result(
+        result: &ResultAndState,
+        to: Address,
+        input: Bytes,
+        access_list: AccessList,
+        decode: impl FnOnce(&Bytes) -> Result<T>,
+    ) -> Result<Self> {
+        let (output, logs, gas_used, gas_refunded) = extract_gas_output_and_logs(&result.result)?;
+        let decoded = decode(&output)?;
+
+        Ok(Self {
+            to,
+            input,
+            status: ExecutionStatus::from(&result.result),
+            output,
+            decoded,
+            gas: GasReport {
+                gas_used,
+                gas_refunded,
+            },
+            gas_with_access_list: None,
+            logs,
+            access_list,
+            access_list_gas_saved: None,
+            state_diff: extract_state_diff(&result.state),
+        })
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> SimulationOutcome<U> {
+        SimulationOutcome {
+            to: self.to,
+            input: self.input,
+            status: self.status,
+            output: self.output,
+            decoded: f(self.decoded),
+            gas: self.gas,
+            gas_with_access_list: self.gas_with_access_list,
+            logs: self.logs,
+            access_list: self.access_list,
+            access_list_gas_saved: self.access_list_gas_saved,
+            state_diff: self.state_diff,
+        }
+    }
+}
+
+impl SimulationOutcome<()> {
+    /// Builds the outcome of an execution without decoding its output, which is the revert
+    /// payload when the call reverted. Reverted and halted executions have no logs.
+    pub fn from_execution(
+        result: &ResultAndState,
+        to: Address,
+        input: Bytes,
+        access_list: AccessList,
+    ) -> Result<Self> {
+        let (output, gas_used) = match &result.result {
+            ExecutionResult::Success { .. } => {
+                return Self::from_result(result, to, input, access_list, |_| Ok(()))
+            }
+            ExecutionResult::Revert { output, gas_used } => (output.clone(), *gas_used),
+            ExecutionResult::Halt { gas_used, .. } => (Bytes::new(), *gas_used),
+        };
+
+        Ok(Self {
+            to,
+            input,
+            status: ExecutionStatus::from(&result.result),
+            output,
+            decoded: (),
+            gas: GasReport {
+                gas_used,
+                gas_refunded: 0,
+            },
+            gas_with_access_list: None,
+            logs: Vec::new(),
+            access_list,
+            access_list_gas_saved: None,
+            state_diff: extract_state_diff(&result.state),
+        })
+    }
+}
+
+impl<T: fmt::Debug> SimulationOutcome<T> {
+    /// Type-erases the decoded value so outcomes of different calls can be reported together.
+    pub fn into_step(self, name: &str) -> SimulationStep {
+        SimulationStep {
+            name: name.to_owned(),
+            outcome: self.map(|decoded| format!("{decoded:?}")),
+        }
+    }
+}
+
+/// A named call performed as part of a larger simulation, e.g. `ERC20 Transfer`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationStep {
+    pub name: String,
+    #[serde(flatten)]
+    pub outcome: SimulationOutcome<String>,
+}
+
+impl fmt::Display for SimulationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.name;
+        let outcome = &self.outcome;
+
+        writeln!(f, "{name} - Status: {}", outcome.status)?;
+        writeln!(
+            f,
+            "{name} - Gas used with no access list: {}",
+            outcome.gas.gas_used
+        )?;
+        writeln!(
+            f,
+            "{name} - Gas refunded with no access list: {}",
+            outcome.gas.gas_refunded
+        )?;
+        if let Some(gas) = outcome.gas_with_access_list {
+            writeln!(f, "{name} - Gas used with access list: {}", gas.gas_used)?;
+            writeln!(
+                f,
+                "{name} - Gas refunded with access list: {}",
+                gas.gas_refunded
+            )?;
+        }
+        match outcome.access_list_gas_saved {
+            Some(gas_saved) => writeln!(f, "{name} - Gas saved by the access list: {gas_saved}")?,
+            None => writeln!(
+                f,
+                "{name} - Access list not attached as it does not save gas"
+            )?,
+        }
+        write!(f, "{name} - Output: {}", outcome.decoded)
+    }
+}
+
+/// Amounts exchanged by a simulated swap, along with every call performed to simulate it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapResult {
+    pub pool: Address,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// The calls performed to swap, in order, which [`super::transaction::sign_steps`] turns
+    /// into the transactions to send. The balances given to a funded caller are not part of
+    /// them, so the caller must not be funded when simulating as a real account.
+    pub steps: Vec<SimulationStep>,
+    /// Metadata of the tokens, used to print the amounts in token units. Missing for tokens not
+    /// implementing `decimals`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_in_metadata: Option<TokenMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_out_metadata: Option<TokenMetadata>,
+    /// Tax taken by the input token when transferring it to the pool.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_tax: Option<TransferTax>,
+    /// Tax taken by the output token when the pool transfers it to the caller.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tax: Option<TransferTax>,
+    /// Every pool swapped through, from `pool` to the one sending token out, only present for
+    /// multi-hop swaps.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub route: Vec<Address>,
+    /// Gas saved by swapping with the pools instead of the router, negative when it costs more.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_saved: Option<i64>,
+    /// The signed transactions replaying the swap, only present when a signer is configured.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<SignedTransaction>,
+    /// The submissions of the transactions to the builders, only present with `--submit`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bundles: Vec<BundleSubmission>,
+    /// What happened to the submitted transactions, only present when monitoring them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inclusion: Option<InclusionReport>,
+}
+
+impl fmt::Display for SwapResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+        for transaction in &self.transactions {
+            writeln!(f, "{transaction}")?;
+        }
+        for bundle in &self.bundles {
+            writeln!(f, "{bundle}")?;
+        }
+        if let Some(inclusion) = &self.inclusion {
+            writeln!(f, "{inclusion}")?;
+        }
+        let token_in = self.token_in_metadata.as_ref();
+        let token_out = self.token_out_metadata.as_ref();
+
+        writeln!(f, "Pool: {}", self.pool)?;
+        if !self.route.is_empty() {
+            let route = self
+                .route
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            writeln!(f, "Route: {route}")?;
+        }
+        if let Some(gas_saved) = self.gas_saved {
+            writeln!(f, "Gas saved versus the router: {gas_saved}")?;
+        }
+        writeln!(f, "Token in: {}", format_token(self.token_in, token_in))?;
+        writeln!(f, "Token out: {}", format_token(self.token_out, token_out))?;
+        writeln!(f, "Amount in: {}", format_amount(self.amount_in, token_in))?;
+        if let Some(input_tax) = &self.input_tax {
+            writeln!(f, "Input transfer tax: {}", input_tax.format(token_in))?;
+        }
+        if let Some(output_tax) = &self.output_tax {
+            writeln!(f, "Output transfer tax: {}", output_tax.format(token_out))?;
+        }
+        write!(
+            f,
+            "Amount out: {}",
+            format_amount(self.amount_out, token_out)
+        )
+    }
+}
+
+/// Formats a raw amount along with its value in token units when the metadata of the token is
+/// known.
+pub fn format_amount(amount: U256, metadata: Option<&TokenMetadata>) -> String {
+    match metadata {
+        Some(metadata) => metadata.format_amount(amount),
+        None => amount.to_string(),
+    }
+}
+
+fn format_token(token: Address, metadata: Option<&TokenMetadata>) -> String {
+    match metadata {
+        Some(metadata) if !metadata.symbol.is_empty() => format!("{token} ({})", metadata.symbol),
+        _ => token.to_string(),
+    }
+}
+
+/// Difference between the amount sent by a transfer and the amount the recipient got, which is
+/// not zero for fee-on-transfer tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TransferTax {
+    pub sent: U256,
+    pub received: U256,
+}
+
+impl TransferTax {
+    pub fn amount(&self) -> U256 {
+        self.sent.saturating_sub(self.received)
+    }
+
+    /// The tax in basis points of the amount sent.
+    pub fn basis_points(&self) -> U256 {
+        if self.sent.is_zero() {
+            return U256::ZERO;
+        }
+
+        self.amount() * U256::from(10_000) / self.sent
+    }
+
+    pub fn format(&self, metadata: Option<&TokenMetadata>) -> String {
+        format!(
+            "{} ({} bps)",
+            format_amount(self.amount(), metadata),
+            self.basis_points()
+        )
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+
+use alloy_sol_types::{sol, PanicKind, SolError, SolValue};
+use lazy_static::lazy_static;
+use revm::primitives::{Bytes, HaltReason, OutOfGasError, U256};
+use serde::Serialize;
+
+// Custom errors of the contracts the simulations usually interact with. Uniswap V2 and the
+// Uniswap V3 pools revert with `Error(string)`, hence only the Universal Router errors are listed
+// for Uniswap.
+sol! {
+    #[derive(Debug)]
+    error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed);
+    #[derive(Debug)]
+    error ERC20InvalidSender(address sender);
+    #[derive(Debug)]
+    error ERC20InvalidReceiver(address receiver);
+    #[derive(Debug)]
+    error ERC20InsufficientAllowance(address spender, uint256 allowance, uint256 needed);
+    #[derive(Debug)]
+    error ERC20InvalidApprover(address approver);
+    #[derive(Debug)]
+    error ERC20InvalidSpender(address spender);
+
+    #[derive(Debug)]
+    error AllowanceExpired(uint256 deadline);
+    #[derive(Debug)]
+    error InsufficientAllowance(uint256 amount);
+    #[derive(Debug)]
+    error ExcessiveInvalidation();
+    #[derive(Debug)]
+    error SignatureExpired(uint256 signatureDeadline);
+    #[derive(Debug)]
+    error InvalidNonce();
+    #[derive(Debug)]
+    error InvalidSignature();
+    #[derive(Debug)]
+    error InvalidSigner();
+    #[derive(Debug)]
+    error InvalidAmount(uint256 maxAmount);
+    #[derive(Debug)]
+    error LengthMismatch();
+
+    #[derive(Debug)]
+    error V2TooLittleReceived();
+    #[derive(Debug)]
+    error V2TooMuchRequested();
+    #[derive(Debug)]
+    error V2InvalidPath();
+    #[derive(Debug)]
+    error V3InvalidSwap();
+    #[derive(Debug)]
+    error V3TooLittleReceived();
+    #[derive(Debug)]
+    error V3TooMuchRequested();
+    #[derive(Debug)]
+    error V3InvalidAmountOut();
+    #[derive(Debug)]
+    error V3InvalidCaller();
+    #[derive(Debug)]
+    error TransactionDeadlinePassed();
+    #[derive(Debug)]
+    error InsufficientETH();
+    #[derive(Debug)]
+    error InsufficientToken();
+}
+
+// Our Uniswap V3 simulator catches the reverts of the pool and reverts again with
+// `Error("UNISWAP_V3 Revert: " ++ <original revert payload>)`.
+const SIMULATOR_REVERT_PREFIX: &[u8] = b"UNISWAP_V3 Revert: ";
+
+struct KnownError {
+    source: &'static str,
+    selector: [u8; 4],
+    decode: fn(&[u8]) -> Option<String>,
+}
+
+fn decode_error<E: SolError + fmt::Debug>(data: &[u8]) -> Option<String> {
+    E::abi_decode_raw(data, true)
+        .ok()
+        .map(|error| format!("{error:?}"))
+}
+
+fn known<E: SolError + fmt::Debug>(source: &'static str) -> KnownError {
+    KnownError {
+        source,
+        selector: E::SELECTOR,
+        decode: decode_error::<E>,
+    }
+}
+
+lazy_static! {
+    static ref KNOWN_ERRORS: Vec<KnownError> = vec![
+        known::<ERC20InsufficientBalance>("ERC20"),
+        known::<ERC20InvalidSender>("ERC20"),
+        known::<ERC20InvalidReceiver>("ERC20"),
+        known::<ERC20InsufficientAllowance>("ERC20"),
+        known::<ERC20InvalidApprover>("ERC20"),
+        known::<ERC20InvalidSpender>("ERC20"),
+        known::<AllowanceExpired>("Permit2"),
+        known::<InsufficientAllowance>("Permit2"),
+        known::<ExcessiveInvalidation>("Permit2"),
+        known::<SignatureExpired>("Permit2"),
+        known::<InvalidNonce>("Permit2"),
+        known::<InvalidSignature>("Permit2"),
+        known::<InvalidSigner>("Permit2"),
+        known::<InvalidAmount>("Permit2"),
+        known::<LengthMismatch>("Permit2"),
+        known::<V2TooLittleReceived>("Uniswap Universal Router"),
+        known::<V2TooMuchRequested>("Uniswap Universal Router"),
+        known::<V2InvalidPath>("Uniswap Universal Router"),
+        known::<V3InvalidSwap>("Uniswap Universal Router"),
+        known::<V3TooLittleReceived>("Uniswap Universal Router"),
+        known::<V3TooMuchRequested>("Uniswap Universal Router"),
+        known::<V3InvalidAmountOut>("Uniswap Universal Router"),
+        known::<V3InvalidCaller>("Uniswap Universal Router"),
+        known::<TransactionDeadlinePassed>("Uniswap Universal Router"),
+        known::<InsufficientETH>("Uniswap Universal Router"),
+        known::<InsufficientToken>("Uniswap Universal Router"),
+    ];
+}
+
+// Uniswap V3 reverts with short codes to save gas, see
+// https://docs.uniswap.org/contracts/v3/reference/error-codes
+fn describe_uniswap_v3_code(code: &str) -> Option<&'static str> {
+    let description = match code {
+        "LOK" => "the pool is locked (reentrancy)",
+        "TLU" => "the lower tick must be below the upper tick",
+        "TLM" => "the lower tick must be greater than the minimum tick",
+        "TUM" => "the upper tick must be lesser than the maximum tick",
+        "AI" => "the pool is already initialized",
+        "M0" => "the token0 balance of the pool did not increase when minting",
+        "M1" => "the token1 balance of the pool did not increase when minting",
+        "AS" => "the amount specified cannot be zero",
+        "SPL" => "the square root price limit is out of bounds",
+        "IIA" => "insufficient input amount, the callback did not pay the pool",
+        "L" => "the pool has no liquidity",
+        "F0" => "the token0 balance of the pool did not increase after the flash loan",
+        "F1" => "the token1 balance of the pool did not increase after the flash loan",
+        "T" => "the tick is out of bounds",
+        "R" => "the square root price is out of bounds",
+        "TF" => "the token transfer failed",
+        "STF" => "the token transfer from the payer failed",
+        "Too little received" => "the output amount is below the minimum amount out",
+        "Too much requested" => "the input amount is above the maximum amount in",
+        "Transaction too old" => "the deadline has passed",
+        _ => return None,
+    };
+
+    Some(description)
+}
+
+/// Reason why a call reverted, decoded from its revert payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum RevertReason {
+    /// The call reverted without any data, e.g. `revert()` or a failed `require` without message.
+    Empty,
+    /// `Error(string)`, the reason of `require(condition, "message")`.
+    Message(String),
+    /// `Panic(uint256)`, raised by failed assertions, overflows, divisions by zero, ...
+    Panic { code: U256, description: String },
+    /// A custom error found in the registry of known errors.
+    Custom { source: String, error: String },
+    /// The revert of a pool unwrapped from the payload of our Uniswap V3 simulator.
+    UniswapV3Simulator(Box<RevertReason>),
+    /// A payload that could not be decoded.
+    Unknown(Bytes),
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "reverted without a reason"),
+            Self::Message(message) => match describe_uniswap_v3_code(message) {
+                Some(description) => write!(f, "{message} ({description})"),
+                None => write!(f, "{message}"),
+            },
+            Self::Panic { code, description } => write!(f, "panic 0x{code:02x}: {description}"),
+            Self::Custom { source, error } => write!(f, "{source} {error}"),
+            Self::UniswapV3Simulator(reason) => write!(f, "Uniswap V3 pool reverted: {reason}"),
+            Self::Unknown(output) => write!(f, "unknown revert payload {output}"),
+        }
+    }
+}
+
+/// Decodes the payload returned by a reverted call.
+pub fn decode_revert(output: &[u8]) -> RevertReason {
+    if output.is_empty() {
+        return RevertReason::Empty;
+    }
+
+    let Some((selector, data)) = output.split_first_chunk::<4>() else {
+        return RevertReason::Unknown(Bytes::copy_from_slice(output));
+    };
+
+    if *selector == alloy_sol_types::Revert::SELECTOR {
+        // The reason is decoded as bytes instead of a string, as the simulator appends the raw
+        // revert payload of the pool to its message, which is rarely valid UTF-8.
+        if let Ok(message) = <Bytes>::abi_decode(data, false) {
+            if let Some(nested) = message.strip_prefix(SIMULATOR_REVERT_PREFIX) {
+                return RevertReason::UniswapV3Simulator(Box::new(decode_revert(nested)));
+            }
+
+            return RevertReason::Message(String::from_utf8_lossy(&message).into_owned());
+        }
+    }
+
+    if *selector == alloy_sol_types::Panic::SELECTOR {
+        if let Ok(code) = <U256>::abi_decode(data, false) {
+            let description = PanicKind::from_number(code.saturating_to())
+                .filter(|_| code <= U256::from(u32::MAX))
+                .map_or("unknown panic code", PanicKind::as_str);
+
+            return RevertReason::Panic {
+                code,
+                description: description.to_owned(),
+            };
+        }
+    }
+
+    let known_error = KNOWN_ERRORS
+        .iter()
+        .filter(|known| known.selector == *selector)
+        .find_map(|known| (known.decode)(data).map(|error| (known.source, error)));
+    if let Some((source, error)) = known_error {
+        return RevertReason::Custom {
+            source: source.to_owned(),
+            error,
+        };
+    }
+
+    // Some contracts (e.g. Vyper ones) revert with a plain message.
+    match std::str::from_utf8(output) {
+        Ok(message) if message.chars().all(|c| !c.is_control()) => {
+            RevertReason::Message(message.to_owned())
+        }
+        _ => RevertReason::Unknown(Bytes::copy_from_slice(output)),
+    }
+}
+
+/// Human-readable explanation of why the execution halted.
+pub fn describe_halt(reason: &HaltReason) -> String {
+    let description = match reason {
+        HaltReason::OutOfGas(OutOfGasError::Basic) => "out of gas",
+        HaltReason::OutOfGas(OutOfGasError::Memory) => "out of gas while expanding the memory",
+        HaltReason::OutOfGas(OutOfGasError::MemoryLimit) => "the memory limit was exceeded",
+        HaltReason::OutOfGas(OutOfGasError::Precompile) => "out of gas in a precompile",
+        HaltReason::OutOfGas(OutOfGasError::InvalidOperand) => {
+            "out of gas, an operand does not fit in 64 bits"
+        }
+        HaltReason::OpcodeNotFound | HaltReason::InvalidFEOpcode => "invalid opcode",
+        HaltReason::NotActivated => "the opcode is not activated in the current fork",
+        HaltReason::InvalidJump => "jump to an invalid destination",
+        HaltReason::StackUnderflow => "stack underflow",
+        HaltReason::StackOverflow => "stack overflow",
+        HaltReason::OutOfOffset => "the offset is out of the bounds of the return data",
+        HaltReason::CreateCollision => "a contract already exists at the created address",
+        HaltReason::PrecompileError => "a precompile failed",
+        HaltReason::NonceOverflow => "the nonce overflowed",
+        HaltReason::CreateContractSizeLimit => "the created contract exceeds the size limit",
+        HaltReason::CreateContractStartingWithEF => "the created contract starts with 0xEF",
+        HaltReason::CreateInitCodeSizeLimit => "the init code exceeds the size limit",
+        HaltReason::OverflowPayment => "the payment overflowed",
+        HaltReason::StateChangeDuringStaticCall | HaltReason::CallNotAllowedInsideStatic => {
+            "state change attempted during a static call"
+        }
+        HaltReason::OutOfFunds => "not enough funds to transfer the value",
+        HaltReason::CallTooDeep => "the call stack is too deep",
+        reason => return format!("{reason:?}"),
+    };
+
+    description.to_owned()
+}
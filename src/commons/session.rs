@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+
+use alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::Block;
+use alloy_transport_http::Http;
+use reqwest::Client;
+use revm::db::{AlloyDB, CacheDB};
+use revm::inspectors::NoOpInspector;
+use revm::primitives::{
+    AccessList, Address, BlockEnv, Bytes, CfgEnv, ExecutionResult, ResultAndState, SpecId, TxEnv,
+    TxKind, U256,
+};
+use revm::{inspector_handle_register, Evm, GetInspector, InMemoryDB};
+
+use super::access_list::AccessListInspector;
+use super::erc20::TokenMetadata;
+use super::errors::{Result, SimulationError};
+use super::helpers::{extract_gas_output_and_logs, fetch_block, AlloyCacheDB, SimulationDatabase};
+use super::results::{GasReport, SimulationOutcome};
+use super::storage::StorageMapping;
+
+/// Intrinsic gas of a transaction without calldata.
+const MIN_TRANSACTION_GAS: u64 = 21_000;
+
+// Block numbers or timestamps at which the forks activate, latest first.
+type Activations = [(u64, SpecId)];
+
+/// Hardfork active at a block of the chain. Mainnet and Sepolia are known, the other chains run
+/// with Cancun. Forks newer than Prague, which the EVM does not implement, run with Prague.
+pub fn spec_id_at(chain_id: u64, number: u64, timestamp: u64) -> SpecId {
+    // Activation timestamps of Shanghai, Cancun and Prague, then the block numbers of the
+    // earlier forks still relevant for simulations.
+    let (timestamps, numbers): (&Activations, &Activations) = match chain_id {
+        1 => (
+            &[
+                (1_746_612_311, SpecId::PRAGUE),
+                (1_710_338_135, SpecId::CANCUN),
+                (1_681_338_455, SpecId::SHANGHAI),
+            ],
+            &[
+                (15_537_394, SpecId::MERGE),
+                (15_050_000, SpecId::GRAY_GLACIER),
+                (13_773_000, SpecId::ARROW_GLACIER),
+                (12_965_000, SpecId::LONDON),
+                (12_244_000, SpecId::BERLIN),
+                (9_200_000, SpecId::MUIR_GLACIER),
+                (9_069_000, SpecId::ISTANBUL),
+                (7_280_000, SpecId::PETERSBURG),
+                (4_370_000, SpecId::BYZANTIUM),
+            ],
+        ),
+        11_155_111 => (
+            &[
+                (1_741_159_776, SpecId::PRAGUE),
+                (1_706_655_072, SpecId::CANCUN),
+                (1_677_557_088, SpecId::SHANGHAI),
+            ],
+            &[(1_735_371, SpecId::MERGE), (0, SpecId::LONDON)],
+        ),
+        _ => return SpecId::CANCUN,
+    };
+
+    timestamps
+        .iter()
+        .find(|(activation, _)| timestamp >= *activation)
+        .or_else(|| numbers.iter().find(|(activation, _)| number >= *activation))
+        .map_or(SpecId::SPURIOUS_DRAGON, |(_, spec_id)| *spec_id)
+}
+
+/// How a state changing call is executed by [`SimulationSession::execute`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecutionStrategy {
+    /// Executes the call once without committing its changes.
+    DryRun,
+    /// Executes the call once and commits its changes.
+    Commit,
+    /// Executes the call a second time including the generated access list, verifies both
+    /// executions produce the same output and logs, and commits the cheapest one.
+    #[default]
+    TwoPass,
+}
+
+/// A fork of the chain at a given block on which simulations are executed.
+///
+/// The session carries the block environment of the fetched block (number, timestamp, basefee,
+/// coinbase, ...), the chain configuration and the caller, so every call executed through it
+/// sees the same environment the transactions would see on chain.
+///
+/// Every change performed through the session (balances, approvals, swaps) lives only in its
+/// local cache, so a fresh session must be forked to start from the real chain state again.
+pub struct SimulationSession<DB> {
+    database: DB,
+    block: Block,
+    block_env: BlockEnv,
+    cfg_env: CfgEnv,
+    spec_id: SpecId,
+    strategy: ExecutionStrategy,
+    caller: Address,
+    /// Whether the caller pays for the gas with its own balance.
+    balance_check: bool,
+    /// Storage mappings discovered so far, by contract and getter selector.
+    storage_mappings: HashMap<(Address, [u8; 4]), StorageMapping>,
+    /// Metadata of the tokens read so far.
+    token_metadata: HashMap<Address, TokenMetadata>,
+}
+
+impl<DB: SimulationDatabase> SimulationSession<DB> {
+    pub fn new(database: DB, block: Block, chain_id: u64, caller: Address) -> Self {
+        let header = &block.header;
+
+        let mut block_env = BlockEnv {
+            number: U256::from(header.number),
+            coinbase: header.miner,
+            timestamp: U256::from(header.timestamp),
+            gas_limit: U256::from(header.gas_limit),
+            basefee: U256::from(header.base_fee_per_gas.unwrap_or_default()),
+            difficulty: header.difficulty,
+            prevrandao: Some(header.mix_hash.unwrap_or_default()),
+            blob_excess_gas_and_price: None,
+        };
+        block_env.set_blob_excess_gas_and_price(header.excess_blob_gas.unwrap_or_default());
+
+        let spec_id = spec_id_at(chain_id, header.number, header.timestamp);
+
+        let mut cfg_env = CfgEnv::default().with_chain_id(chain_id);
+        // Simulations are not meant to fail because the caller cannot prepay the gas limit of
+        // the whole block. The missing funds are added to the caller balance when needed, unless
+        // the balance check is enabled with `with_balance_check`.
+        cfg_env.disable_balance_check = true;
+
+        Self {
+            database,
+            block,
+            block_env,
+            cfg_env,
+            spec_id,
+            strategy: ExecutionStrategy::default(),
+            caller,
+            balance_check: false,
+            storage_mappings: HashMap::new(),
+            token_metadata: HashMap::new(),
+        }
+    }
+
+    /// Overrides the hardfork derived from the chain and the block.
+    pub fn with_spec_id(mut self, spec_id: SpecId) -> Self {
+        self.spec_id = spec_id;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: ExecutionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Makes the caller pay for the gas with its own balance, as it would on chain, instead of
+    /// being funded with whatever the call needs. Required when simulating as a real account.
+    pub fn with_balance_check(mut self, enabled: bool) -> Self {
+        self.balance_check = enabled;
+        self.cfg_env.disable_balance_check = !enabled;
+        self
+    }
+
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+
+    pub fn block_env(&self) -> &BlockEnv {
+        &self.block_env
+    }
+
+    pub fn cfg_env(&self) -> &CfgEnv {
+        &self.cfg_env
+    }
+
+    pub fn spec_id(&self) -> SpecId {
+        self.spec_id
+    }
+
+    pub fn strategy(&self) -> ExecutionStrategy {
+        self.strategy
+    }
+
+    pub fn caller(&self) -> Address {
+        self.caller
+    }
+
+    pub fn database(&mut self) -> &mut DB {
+        &mut self.database
+    }
+
+    /// Current nonce of `address` in the fork, including the transactions committed so far.
+    pub fn nonce(&mut self, address: Address) -> Result<u64> {
+        let account = self.database.basic(address).map_err(SimulationError::rpc)?;
+
+        Ok(account.map_or(0, |account| account.nonce))
+    }
+
+    pub fn storage_mapping(&self, contract: Address, selector: [u8; 4]) -> Option<StorageMapping> {
+        self.storage_mappings.get(&(contract, selector)).copied()
+    }
+
+    pub fn cache_storage_mapping(
+        &mut self,
+        contract: Address,
+        selector: [u8; 4],
+        mapping: StorageMapping,
+    ) {
+        self.storage_mappings.insert((contract, selector), mapping);
+    }
+
+    pub fn token_metadata(&self, token: Address) -> Option<&TokenMetadata> {
+        self.token_metadata.get(&token)
+    }
+
+    pub fn cache_token_metadata(&mut self, token: Address, metadata: TokenMetadata) {
+        self.token_metadata.insert(token, metadata);
+    }
+
+    /// Executes a read-only call and decodes its output. Nothing is committed.
+    pub fn call<T>(
+        &mut self,
+        to: Address,
+        calldata: Bytes,
+        decode: impl Fn(&Bytes) -> Result<T>,
+    ) -> Result<SimulationOutcome<T>> {
+        self.execute_with(ExecutionStrategy::DryRun, to, calldata, decode)
+    }
+
+    /// Executes a state changing call following the session strategy.
+    pub fn execute<T>(
+        &mut self,
+        to: Address,
+        calldata: Bytes,
+        decode: impl Fn(&Bytes) -> Result<T>,
+    ) -> Result<SimulationOutcome<T>> {
+        self.execute_with(self.strategy, to, calldata, decode)
+    }
+
+    /// Executes a state changing call following the given strategy.
+    pub fn execute_with<T>(
+        &mut self,
+        strategy: ExecutionStrategy,
+        to: Address,
+        calldata: Bytes,
+        decode: impl Fn(&Bytes) -> Result<T>,
+    ) -> Result<SimulationOutcome<T>> {
+        // Checkpoint:
+        // First execution ensures that the transaction is valid and also allows us to record
+        // the accounts and storage slots that were accessed. At this point it is important not
+        // to commit the changes to the database.
+        let (result, access_list) = self.transact_with_access_list(to, calldata.clone())?;
+        let mut outcome =
+            SimulationOutcome::from_result(&result, to, calldata.clone(), access_list, &decode)?;
+        let mut selected = result;
+
+        match strategy {
+            ExecutionStrategy::DryRun => return Ok(outcome),
+            ExecutionStrategy::Commit => {}
+            // Checkpoint:
+            // We execute the same transaction but this time including the access list, which
+            // tells us whether warming up the accounts and slots upfront is cheaper than
+            // accessing them cold. Both executions must behave the same, otherwise the access
+            // list would change the result of the transaction once sent.
+            ExecutionStrategy::TwoPass if !outcome.access_list.is_empty() => {
+                let result = self.transact(to, calldata, &outcome.access_list)?;
+                let (output, logs, gas_used, gas_refunded) =
+                    extract_gas_output_and_logs(&result.result)?;
+
+                if output != outcome.output {
+                    return Err(SimulationError::InvariantViolation(format!(
+                        "The output of the call to {to} differs when including the access list"
+                    )));
+                }
+                if logs != outcome.logs {
+                    return Err(SimulationError::InvariantViolation(format!(
+                        "The logs of the call to {to} differ when including the access list"
+                    )));
+                }
+
+                outcome.gas_with_access_list = Some(GasReport {
+                    gas_used,
+                    gas_refunded,
+                });
+
+                if gas_used < outcome.gas.gas_used {
+                    outcome.access_list_gas_saved = Some(outcome.gas.gas_used - gas_used);
+                    selected = result;
+                }
+            }
+            ExecutionStrategy::TwoPass => {}
+        }
+
+        // It is important to commit the changes or the following calls (e.g. a swap via the
+        // router performing a `transfer_from`) will fail.
+        self.database.commit(selected.state);
+
+        Ok(outcome)
+    }
+
+    /// Executes a call from the session caller without committing its changes, recording the
+    /// EIP-2930 access list it needs.
+    pub fn transact_with_access_list(
+        &mut self,
+        to: Address,
+        calldata: Bytes,
+    ) -> Result<(ResultAndState, AccessList)> {
+        let tx_env = self.tx_env(to, calldata, &AccessList::default())?;
+
+        let mut inspector = AccessListInspector::default();
+        let result = self.evm(tx_env, &mut inspector).transact()?;
+
+        Ok((result, inspector.into_access_list()))
+    }
+
+    /// Executes a call from the session caller with the given inspector, without committing its
+    /// changes.
+    pub fn inspect<'a, I: GetInspector<&'a mut DB> + 'a>(
+        &'a mut self,
+        to: Address,
+        calldata: Bytes,
+        inspector: I,
+    ) -> Result<ResultAndState> {
+        let tx_env = self.tx_env(to, calldata, &AccessList::default())?;
+        let result = self.evm(tx_env, inspector).transact()?;
+
+        Ok(result)
+    }
+
+    /// Executes a call from the session caller without committing its changes.
+    pub fn transact(
+        &mut self,
+        to: Address,
+        calldata: Bytes,
+        access_list: &AccessList,
+    ) -> Result<ResultAndState> {
+        let tx_env = self.tx_env(to, calldata, access_list)?;
+        let result = self.evm(tx_env, NoOpInspector).transact()?;
+
+        Ok(result)
+    }
+
+    /// Executes a call from the session caller and commits its changes into the database.
+    pub fn transact_commit(
+        &mut self,
+        to: Address,
+        calldata: Bytes,
+        access_list: &AccessList,
+    ) -> Result<ExecutionResult> {
+        let tx_env = self.tx_env(to, calldata, access_list)?;
+        let result = self.evm(tx_env, NoOpInspector).transact_commit()?;
+
+        Ok(result)
+    }
+
+    fn tx_env(&mut self, to: Address, calldata: Bytes, access_list: &AccessList) -> Result<TxEnv> {
+        let gas_price = self.block_env.basefee;
+        let mut gas_limit: u64 = self.block_env.gas_limit.saturating_to();
+
+        // As `eth_call` does, the gas limit is capped to what the caller can pay for, so a real
+        // account is not required to prepay the gas limit of the whole block.
+        if self.balance_check && !gas_price.is_zero() {
+            let balance = self
+                .database
+                .basic(self.caller)
+                .map_err(SimulationError::rpc)?
+                .map_or(U256::ZERO, |account| account.balance);
+            gas_limit = gas_limit.min((balance / gas_price).saturating_to());
+
+            if gas_limit < MIN_TRANSACTION_GAS {
+                return Err(SimulationError::Evm(format!(
+                    "The caller {} cannot pay for the gas with a balance of {balance} wei",
+                    self.caller
+                )));
+            }
+        }
+
+        Ok(TxEnv {
+            caller: self.caller,
+            transact_to: TxKind::Call(to),
+            data: calldata,
+            value: U256::ZERO,
+            gas_limit,
+            gas_price,
+            access_list: access_list.0.clone(),
+            ..Default::default()
+        })
+    }
+
+    fn evm<'a, I: GetInspector<&'a mut DB>>(
+        &'a mut self,
+        tx_env: TxEnv,
+        inspector: I,
+    ) -> Evm<'a, I, &'a mut DB> {
+        Evm::builder()
+            .with_db(&mut self.database)
+            .with_external_context(inspector)
+            .with_spec_id(self.spec_id)
+            .modify_cfg_env(|cfg| *cfg = self.cfg_env.clone())
+            .with_block_env(self.block_env.clone())
+            .with_tx_env(tx_env)
+            .append_handler_register(inspector_handle_register)
+            .build()
+    }
+}
+
+impl<'a> SimulationSession<AlloyCacheDB<'a>> {
+    pub async fn fork(
+        client: &'a RootProvider<Http<Client>>,
+        block: &Block,
+        caller: Address,
+    ) -> Result<Self> {
+        let chain_id = client.get_chain_id().await.map_err(SimulationError::rpc)?;
+
+        let block_id = BlockId::Number(BlockNumberOrTag::Number(block.header.number));
+        let database = AlloyDB::new(client, block_id).ok_or_else(|| {
+            SimulationError::Config("A multi-threaded tokio runtime is required to fork".to_owned())
+        })?;
+
+        Ok(Self::new(
+            CacheDB::new(database),
+            block.clone(),
+            chain_id,
+            caller,
+        ))
+    }
+
+    /// Fetches the block identified by `block_id` (number, hash or tag) and forks the chain at
+    /// it, which allows reproducing simulations against past blocks.
+    pub async fn fork_at(
+        client: &'a RootProvider<Http<Client>>,
+        block_id: BlockId,
+        caller: Address,
+    ) -> Result<Self> {
+        let block = fetch_block(client, block_id).await?;
+
+        Self::fork(client, &block, caller).await
+    }
+}
+
+impl SimulationSession<InMemoryDB> {
+    /// Creates a session backed by an empty in-memory database, which is useful to run
+    /// simulations offline against contracts deployed by hand.
+    pub fn in_memory(block: Block, caller: Address) -> Self {
+        Self::new(InMemoryDB::default(), block, 1, caller)
+    }
+}
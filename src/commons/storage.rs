@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use revm::interpreter::{opcode, Interpreter};
+use revm::primitives::{keccak256, Address, Bytes, B256, U256};
+use revm::{Database, EvmContext, Inspector};
+
+use super::errors::{Result, SimulationError};
+use super::helpers::{set_storage, SimulationDatabase};
+use super::session::SimulationSession;
+
+/// Order in which a mapping hashes its key and its slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashOrder {
+    /// `keccak256(key . slot)`, used by Solidity.
+    KeyFirst,
+    /// `keccak256(slot . key)`, used by Vyper.
+    SlotFirst,
+}
+
+/// Location of a (possibly nested) mapping of a contract, along with where its values live in
+/// the storage words, as values smaller than 256 bits may be packed with other variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageMapping {
+    /// The account holding the storage, which differs from the contract when it keeps its state
+    /// in a separate contract.
+    pub storage: Address,
+    /// The declaration slot of the mapping. For namespaced storage (ERC-7201) this is the slot
+    /// derived from the namespace.
+    pub base: B256,
+    pub order: HashOrder,
+    /// Position of the lowest bit of the value in the storage word.
+    pub shift: usize,
+    /// Number of bits of the value.
+    pub width: usize,
+}
+
+impl StorageMapping {
+    /// The slot holding the value of `keys`, from the outermost mapping to the innermost one.
+    pub fn slot(&self, keys: &[B256]) -> U256 {
+        let slot = keys.iter().fold(self.base, |slot, key| match self.order {
+            HashOrder::KeyFirst => keccak256([key.as_slice(), slot.as_slice()].concat()),
+            HashOrder::SlotFirst => keccak256([slot.as_slice(), key.as_slice()].concat()),
+        });
+
+        slot.into()
+    }
+
+    fn mask(&self) -> U256 {
+        if self.width == 256 {
+            U256::MAX
+        } else {
+            ((U256::from(1) << self.width) - U256::from(1)) << self.shift
+        }
+    }
+
+    /// Replaces the value in `word`, leaving the variables packed with it untouched. `None`
+    /// when the value does not fit.
+    pub fn pack(&self, word: U256, value: U256) -> Option<U256> {
+        if value.bit_len() > self.width {
+            return None;
+        }
+
+        Some((word & !self.mask()) | (value << self.shift))
+    }
+
+    /// Writes the value of `keys` in the database.
+    pub fn write<DB: SimulationDatabase>(
+        &self,
+        keys: &[B256],
+        value: U256,
+        database: &mut DB,
+    ) -> Result<()> {
+        let slot = self.slot(keys);
+        let word = database
+            .storage(self.storage, slot)
+            .map_err(SimulationError::rpc)?;
+
+        let word = self.pack(word, value).ok_or_else(|| {
+            SimulationError::Config(format!(
+                "{value} does not fit in the {} bits the contract stores it in",
+                self.width
+            ))
+        })?;
+
+        set_storage(self.storage, slot, word, database)
+    }
+}
+
+/// Inspector recording the storage slots read by a call, along with the 64 bytes preimages of
+/// the hashes it computed, which is how mapping slots are derived.
+#[derive(Debug, Default)]
+pub struct SlotTracer {
+    sloads: Vec<(Address, B256)>,
+    preimages: HashMap<B256, (B256, B256)>,
+}
+
+impl SlotTracer {
+    /// Finds the mappings whose slot for `keys` was read, trying both hashing orders.
+    ///
+    /// Every candidate is returned in the order the slots were read, as a call usually reads
+    /// other values derived from the same keys (e.g. a blacklist) besides the one we look for.
+    pub fn candidates(&self, keys: &[B256]) -> Vec<(Address, B256, HashOrder)> {
+        let mut candidates = vec![];
+
+        for &(storage, slot) in &self.sloads {
+            for order in [HashOrder::KeyFirst, HashOrder::SlotFirst] {
+                if let Some(base) = self.unroll(slot, keys, order) {
+                    if !candidates.contains(&(storage, base, order)) {
+                        candidates.push((storage, base, order));
+                    }
+                }
+            }
+        }
+
+        candidates
+    }
+
+    // Walks the hashes back from the innermost key, returning the declaration slot.
+    fn unroll(&self, slot: B256, keys: &[B256], order: HashOrder) -> Option<B256> {
+        let mut slot = slot;
+
+        for key in keys.iter().rev() {
+            let (first, second) = self.preimages.get(&slot)?;
+            slot = match order {
+                HashOrder::KeyFirst if first == key => *second,
+                HashOrder::SlotFirst if second == key => *first,
+                _ => return None,
+            };
+        }
+
+        Some(slot)
+    }
+}
+
+impl<DB: Database> Inspector<DB> for SlotTracer {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let stack = interp.stack();
+
+        match interp.current_opcode() {
+            opcode::SLOAD => {
+                if let Ok(slot) = stack.peek(0) {
+                    self.sloads
+                        .push((interp.contract.target_address, B256::from(slot)));
+                }
+            }
+            opcode::KECCAK256 => {
+                let (Ok(offset), Ok(size)) = (stack.peek(0), stack.peek(1)) else {
+                    return;
+                };
+                if size != U256::from(64) {
+                    return;
+                }
+
+                let offset = offset.saturating_to::<usize>();
+                let memory = interp.shared_memory.context_memory();
+                if let Some(preimage) = memory.get(offset..offset.saturating_add(64)) {
+                    self.preimages.insert(
+                        keccak256(preimage),
+                        (
+                            B256::from_slice(&preimage[..32]),
+                            B256::from_slice(&preimage[32..]),
+                        ),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Finds the mapping read by the getter called with `calldata` like [`discover_mapping`], caching
+/// it in the session for the contract and the getter.
+pub fn find_mapping<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    contract: Address,
+    calldata: Bytes,
+    keys: &[B256],
+) -> Result<Option<StorageMapping>> {
+    let Some(selector) = calldata.first_chunk::<4>().copied() else {
+        return Err(SimulationError::Config(
+            "The getter calldata has no selector".to_owned(),
+        ));
+    };
+
+    if let Some(mapping) = session.storage_mapping(contract, selector) {
+        return Ok(Some(mapping));
+    }
+
+    let mapping = discover_mapping(session, contract, calldata, keys)?;
+    if let Some(mapping) = mapping {
+        session.cache_storage_mapping(contract, selector, mapping);
+    }
+
+    Ok(mapping)
+}
+
+/// Finds the mapping read by the getter called with `calldata`, whose value for `keys` is
+/// returned by the getter as its first word.
+///
+/// The slots read by the getter are traced, and each one derived from the keys is checked by
+/// writing sentinel values into it and reading them back through the getter. Writing ones tells
+/// the width of the value and shifting a sentinel through the word tells where it is packed. The
+/// original values are restored afterwards. `None` when no slot holds the value.
+pub fn discover_mapping<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    contract: Address,
+    calldata: Bytes,
+    keys: &[B256],
+) -> Result<Option<StorageMapping>> {
+    let mut tracer = SlotTracer::default();
+    session.inspect(contract, calldata.clone(), &mut tracer)?;
+
+    for (storage, base, order) in tracer.candidates(keys) {
+        let mut mapping = StorageMapping {
+            storage,
+            base,
+            order,
+            shift: 0,
+            width: 256,
+        };
+        let slot = mapping.slot(keys);
+        let original = session
+            .database()
+            .storage(storage, slot)
+            .map_err(SimulationError::rpc)?;
+
+        let found = probe(session, contract, &calldata, &mut mapping, slot);
+        set_storage(storage, slot, original, session.database())?;
+
+        if found? {
+            return Ok(Some(mapping));
+        }
+    }
+
+    Ok(None)
+}
+
+// Checks whether the value returned by the getter lives in `slot`, adjusting the width and the
+// shift of the mapping.
+fn probe<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    contract: Address,
+    calldata: &Bytes,
+    mapping: &mut StorageMapping,
+    slot: U256,
+) -> Result<bool> {
+    // A value of `width` bits comes back as `2^width - 1`.
+    let Some(ones) = read_through(
+        session,
+        contract,
+        calldata,
+        mapping.storage,
+        slot,
+        U256::MAX,
+    )?
+    else {
+        return Ok(false);
+    };
+    if ones.is_zero() || (ones != U256::MAX && !(ones + U256::from(1)).is_power_of_two()) {
+        return Ok(false);
+    }
+    mapping.width = ones.bit_len();
+
+    let sentinel = U256::from(0x5e471e1u64) & (ones >> 1usize);
+    if sentinel.is_zero() {
+        return Ok(false);
+    }
+    for shift in (0..=256 - mapping.width).step_by(8) {
+        let word = sentinel << shift;
+        let value = read_through(session, contract, calldata, mapping.storage, slot, word)?;
+
+        if value == Some(sentinel) {
+            mapping.shift = shift;
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+// Writes `word` into the slot and returns the first word returned by the getter, if it
+// succeeds.
+fn read_through<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    contract: Address,
+    calldata: &Bytes,
+    storage: Address,
+    slot: U256,
+    word: U256,
+) -> Result<Option<U256>> {
+    set_storage(storage, slot, word, session.database())?;
+
+    let value = session
+        .call(contract, calldata.clone(), |output| {
+            Ok(output.get(..32).map(|value| U256::from_be_slice(value)))
+        })
+        .ok()
+        .and_then(|outcome| outcome.decoded);
+
+    Ok(value)
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+
+use alloy_consensus::{SignableTransaction, TxEip1559, TxEnvelope};
+use alloy_eips::calc_next_block_base_fee;
+use alloy_eips::eip1559::BaseFeeParams;
+use alloy_eips::eip2718::Encodable2718;
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use revm::primitives::{AccessList, Address, Bytes, TxKind, B256, U256};
+use serde::Serialize;
+
+use super::errors::{Result, SimulationError};
+use super::helpers::SimulationDatabase;
+use super::identity::compute_address;
+use super::results::SimulationStep;
+use super::session::SimulationSession;
+
+/// How the fees and the gas limit of the transactions are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionSettings {
+    /// Tip paid to the builder, in wei.
+    pub max_priority_fee_per_gas: u128,
+    /// Extra gas added on top of the gas used in the simulation, as a percentage. The state may
+    /// change before the transaction is included, and calls forward only 63/64 of the remaining
+    /// gas, hence the gas used is not always enough as a limit.
+    pub gas_limit_margin: u64,
+}
+
+impl Default for TransactionSettings {
+    fn default() -> Self {
+        Self {
+            max_priority_fee_per_gas: 1_000_000_000,
+            gas_limit_margin: 20,
+        }
+    }
+}
+
+/// An EIP-1559 transaction built from a simulated step and signed, ready to be sent.
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedTransaction {
+    pub name: String,
+    pub hash: B256,
+    pub from: Address,
+    pub to: Address,
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    /// Gas used by the execution the transaction was built from.
+    pub simulated_gas_used: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    pub input: Bytes,
+    pub access_list: AccessList,
+    /// The EIP-2718 encoding of the signed transaction, as expected by `eth_sendRawTransaction`.
+    pub raw: Bytes,
+}
+
+impl fmt::Display for SignedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.name;
+
+        writeln!(f, "{name} - Transaction hash: {}", self.hash)?;
+        writeln!(f, "{name} - Nonce: {}", self.nonce)?;
+        writeln!(f, "{name} - Gas limit: {}", self.gas_limit)?;
+        writeln!(f, "{name} - Max fee per gas: {}", self.max_fee_per_gas)?;
+        writeln!(
+            f,
+            "{name} - Max priority fee per gas: {}",
+            self.max_priority_fee_per_gas
+        )?;
+        writeln!(
+            f,
+            "{name} - Access list entries: {}",
+            self.access_list.0.len()
+        )?;
+        write!(f, "{name} - Raw transaction: {}", self.raw)
+    }
+}
+
+/// Turns the simulated steps into EIP-1559 transactions sent by the session caller, starting at
+/// `nonce`, and signs them.
+///
+/// The gas limit comes from the gas used in the simulation, and the access list is only attached
+/// when the simulation found it saves gas. The max fee allows the base fee to double from the
+/// next block, which keeps the transactions valid for a few full blocks.
+pub fn sign_steps<DB: SimulationDatabase>(
+    session: &SimulationSession<DB>,
+    steps: &[SimulationStep],
+    nonce: u64,
+    settings: &TransactionSettings,
+    signer: &PrivateKeySigner,
+) -> Result<Vec<SignedTransaction>> {
+    let from = compute_address(signer);
+    if from != session.caller() {
+        return Err(SimulationError::Config(format!(
+            "The signer {from} is not the caller {} of the simulation",
+            session.caller()
+        )));
+    }
+
+    let header = &session.block().header;
+    let base_fee = header.base_fee_per_gas.ok_or_else(|| {
+        SimulationError::Config(format!(
+            "Block {} has no base fee, EIP-1559 is not active",
+            header.number
+        ))
+    })?;
+    let next_base_fee = calc_next_block_base_fee(
+        header.gas_used,
+        header.gas_limit,
+        base_fee,
+        BaseFeeParams::ethereum(),
+    );
+    let max_fee_per_gas = u128::from(next_base_fee) * 2 + settings.max_priority_fee_per_gas;
+
+    let chain_id = session.cfg_env().chain_id;
+    let block_gas_limit = session.block_env().gas_limit.saturating_to::<u64>();
+
+    steps
+        .iter()
+        .zip(nonce..)
+        .map(|(step, nonce)| {
+            let outcome = &step.outcome;
+
+            let (gas, access_list) =
+                match (outcome.access_list_gas_saved, outcome.gas_with_access_list) {
+                    (Some(_), Some(gas)) => (gas, outcome.access_list.clone()),
+                    _ => (outcome.gas, AccessList::default()),
+                };
+
+            // The gas used reported by the EVM has the refund already deducted, but the refund
+            // is only given back at the end of the transaction.
+            let gas_spent = gas.gas_used + gas.gas_refunded;
+            let gas_limit =
+                (gas_spent + gas_spent * settings.gas_limit_margin / 100).min(block_gas_limit);
+
+            let transaction = TxEip1559 {
+                chain_id,
+                nonce,
+                gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas: settings.max_priority_fee_per_gas,
+                to: TxKind::Call(outcome.to),
+                value: U256::ZERO,
+                access_list: access_list.clone(),
+                input: outcome.input.clone(),
+            };
+
+            let signature = signer
+                .sign_hash_sync(&transaction.signature_hash())
+                .map_err(|e| {
+                    SimulationError::Config(format!("Cannot sign the transaction: {e}"))
+                })?;
+            let envelope = TxEnvelope::from(transaction.into_signed(signature));
+
+            Ok(SignedTransaction {
+                name: step.name.clone(),
+                hash: *envelope.tx_hash(),
+                from,
+                to: outcome.to,
+                chain_id,
+                nonce,
+                gas_limit,
+                simulated_gas_used: gas.gas_used,
+                max_fee_per_gas,
+                max_priority_fee_per_gas: settings.max_priority_fee_per_gas,
+                input: outcome.input.clone(),
+                access_list,
+                raw: Bytes::from(envelope.encoded_2718()),
+            })
+        })
+        .collect()
+}
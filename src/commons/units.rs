@@ -0,0 +1,57 @@
+use revm::primitives::U256;
+
+use super::errors::{Result, SimulationError};
+
+/// Parses an amount given in token units, e.g. `1.5`, into the raw amount for a token with
+/// `decimals` decimals.
+pub fn parse_units(amount: &str, decimals: u8) -> Result<U256> {
+    let invalid = || SimulationError::Config(format!("Invalid amount {amount}"));
+
+    let (integer, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+    if !integer
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    if fraction.len() > usize::from(decimals) {
+        return Err(SimulationError::Config(format!(
+            "{amount} has more decimals than the token ({decimals})"
+        )));
+    }
+
+    let digits = format!(
+        "{integer}{fraction:0<width$}",
+        width = usize::from(decimals)
+    );
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(U256::ZERO);
+    }
+
+    digits.parse::<U256>().map_err(|_| invalid())
+}
+
+/// Formats a raw amount in token units, leaving out the trailing zeros of the fractional part.
+pub fn format_units(amount: U256, decimals: u8) -> String {
+    // Tokens with more decimals than a `U256` has digits only have fractional amounts.
+    let (integer, fraction) = match U256::from(10).checked_pow(U256::from(decimals)) {
+        Some(unit) => (amount / unit, amount % unit),
+        None => (U256::ZERO, amount),
+    };
+
+    if fraction.is_zero() {
+        return integer.to_string();
+    }
+
+    let fraction = format!(
+        "{:0>width$}",
+        fraction.to_string(),
+        width = usize::from(decimals)
+    );
+    format!("{integer}.{}", fraction.trim_end_matches('0'))
+}
@@ -0,0 +1,14 @@
+//! Swap simulations on top of `revm`.
+//!
+//! The crate forks the chain at a given block through a [`SimulationSession`] and exposes the
+//! building blocks used by the CLI: ERC20 helpers, Uniswap V2/V3 pool adapters and the swap
+//! simulations themselves.
+
+pub mod commons;
+pub mod token_safety;
+pub mod uniswap_v2;
+pub mod uniswap_v3;
+
+pub use commons::erc20::Erc20;
+pub use commons::results::SwapResult;
+pub use commons::session::{ExecutionStrategy, SimulationSession};
@@ -1,5 +1,6 @@
+use clap::Arg;
+
 mod commands;
-mod commons;
 
 #[tokio::main]
 async fn main() {
@@ -8,7 +9,27 @@ async fn main() {
     let mut clap_commands = clap::Command::new("revm-demo")
         .version("0.1.0")
         .subcommand_required(true)
-        .arg_required_else_help(true);
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .help("The format used to print the simulation results")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .global(true),
+        )
+        .arg(
+            Arg::new("chain")
+                .long("chain")
+                .help("The chain to simulate on, either an id or a name from the registry. Defaults to the chain of the node")
+                .global(true),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("A TOML file extending the built-in registry of chains and protocol addresses")
+                .global(true),
+        );
 
     for command in commands.values() {
         clap_commands = clap_commands.subcommand(command.create());
@@ -19,7 +40,10 @@ async fn main() {
         Some(subcommand) => {
             let (subcommand_name, subcommand_args) = subcommand;
             let command = commands.get(subcommand_name).unwrap();
-            command.execute(subcommand_args).await;
+            if let Err(error) = command.execute(subcommand_args).await {
+                eprintln!("Error: {error}");
+                std::process::exit(error.exit_code());
+            }
         }
         _ => {
             println!("No subcommand provided");
@@ -0,0 +1,461 @@
+use std::fmt;
+
+use revm::primitives::{address, keccak256, Address, Bytes, B256, U256};
+use serde::Serialize;
+
+use crate::commons::erc20::{Erc20, TokenMetadata};
+use crate::commons::errors::{Result, SimulationError};
+use crate::commons::helpers::SimulationDatabase;
+use crate::commons::registry::{ChainConfig, FactoryConfig};
+use crate::commons::results::{format_amount, SwapResult, TransferTax};
+use crate::commons::session::SimulationSession;
+use crate::uniswap_v2;
+use crate::uniswap_v2::v2_math::SwapFee;
+use crate::uniswap_v3;
+
+/// Account the checks are run from by default. It holds nothing on chain, hence the results do not
+/// depend on its history, and it is not exempted from the limits of the tokens.
+pub const DEFAULT_BUYER: Address = address!("5afe7e57000000000000000000000000000b0001");
+
+/// Account receiving the tokens in the transfer check by default.
+pub const DEFAULT_RECIPIENT: Address = address!("5afe7e57000000000000000000000000000b0002");
+
+// Getters commonly exposed by the tokens limiting the size of the transactions or of the wallets.
+const LIMIT_GETTERS: [&str; 8] = [
+    "maxTxAmount()",
+    "_maxTxAmount()",
+    "maxTransactionAmount()",
+    "maxWallet()",
+    "_maxWallet()",
+    "maxWalletSize()",
+    "_maxWalletSize()",
+    "maxWalletAmount()",
+];
+
+// Getters commonly exposed by the tokens keeping a blacklist.
+const BLACKLIST_GETTERS: [&str; 7] = [
+    "isBlacklisted(address)",
+    "_isBlacklisted(address)",
+    "isBlackListed(address)",
+    "blacklisted(address)",
+    "blacklist(address)",
+    "isBot(address)",
+    "bots(address)",
+];
+
+/// The pool the token is traded through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradingPool {
+    UniswapV2(Address),
+    UniswapV3 { pool: Address, simulator: Address },
+}
+
+impl TradingPool {
+    pub fn address(&self) -> Address {
+        match self {
+            Self::UniswapV2(pool) => *pool,
+            Self::UniswapV3 { pool, .. } => *pool,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenSafetyConfig {
+    pub token: Address,
+    pub pool: TradingPool,
+    /// Amount of the other token of the pool spent buying the token.
+    pub amount: U256,
+    /// Account receiving part of the tokens bought in the transfer check.
+    pub recipient: Address,
+    /// Fee taken by the pool when it is a Uniswap V2 pair.
+    pub fee: SwapFee,
+}
+
+impl TokenSafetyConfig {
+    pub fn new(token: Address, pool: TradingPool, amount: U256) -> Self {
+        Self {
+            token,
+            pool,
+            amount,
+            recipient: DEFAULT_RECIPIENT,
+            fee: SwapFee::default(),
+        }
+    }
+
+    pub fn with_recipient(mut self, recipient: Address) -> Self {
+        self.recipient = recipient;
+        self
+    }
+
+    pub fn with_fee(mut self, fee: SwapFee) -> Self {
+        self.fee = fee;
+        self
+    }
+}
+
+/// Outcome of buying, transferring or selling the token.
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeCheck {
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Tax taken by the token. Reported when the trade succeeds and the tax can be measured.
+    pub tax: Option<TransferTax>,
+    /// Why the trade failed.
+    pub error: Option<String>,
+}
+
+impl TradeCheck {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn failed(amount_in: U256, error: SimulationError) -> Self {
+        Self {
+            amount_in,
+            amount_out: U256::ZERO,
+            tax: None,
+            error: Some(error.to_string()),
+        }
+    }
+
+    // Formats the amounts in units of the tokens sent and received. The taxes are always taken
+    // by the checked token.
+    fn format(
+        &self,
+        metadata_in: Option<&TokenMetadata>,
+        metadata_out: Option<&TokenMetadata>,
+        token: Option<&TokenMetadata>,
+    ) -> String {
+        let amount_in = format_amount(self.amount_in, metadata_in);
+        if let Some(error) = &self.error {
+            return format!("failed with {amount_in} in: {error}");
+        }
+
+        let amount_out = format_amount(self.amount_out, metadata_out);
+        match &self.tax {
+            Some(tax) => format!(
+                "{amount_in} in, {amount_out} out, tax {}",
+                tax.format(token)
+            ),
+            None => format!("{amount_in} in, {amount_out} out"),
+        }
+    }
+}
+
+/// Value returned by one of the limit getters of the token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenLimit {
+    pub getter: String,
+    pub value: U256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenSafetyReport {
+    pub token: Address,
+    /// The other token of the pool, used to buy the token.
+    pub base_token: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_metadata: Option<TokenMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_token_metadata: Option<TokenMetadata>,
+    pub pool: Address,
+    pub account: Address,
+    pub buy: TradeCheck,
+    /// Only checked when the buy succeeds.
+    pub transfer: Option<TradeCheck>,
+    /// Only checked when the buy succeeds.
+    pub sell: Option<TradeCheck>,
+    pub limits: Vec<TokenLimit>,
+    /// The blacklist getters flagging the account after the buy.
+    pub blacklisted_by: Vec<String>,
+}
+
+impl TokenSafetyReport {
+    /// Whether the tokens bought can be sold back and transferred.
+    pub fn is_tradable(&self) -> bool {
+        let passed =
+            |check: &Option<TradeCheck>| check.as_ref().is_some_and(TradeCheck::is_success);
+
+        self.buy.is_success() && passed(&self.sell) && passed(&self.transfer)
+    }
+}
+
+impl fmt::Display for TokenSafetyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Token: {}", self.token)?;
+        writeln!(f, "Base token: {}", self.base_token)?;
+        writeln!(f, "Pool: {}", self.pool)?;
+        writeln!(f, "Account: {}", self.account)?;
+        let token = self.token_metadata.as_ref();
+        let base_token = self.base_token_metadata.as_ref();
+
+        writeln!(f, "Buy: {}", self.buy.format(base_token, token, token))?;
+        match &self.transfer {
+            Some(transfer) => writeln!(f, "Transfer: {}", transfer.format(token, token, token))?,
+            None => writeln!(f, "Transfer: not checked")?,
+        }
+        match &self.sell {
+            Some(sell) => writeln!(f, "Sell: {}", sell.format(token, base_token, token))?,
+            None => writeln!(f, "Sell: not checked")?,
+        }
+        for limit in &self.limits {
+            writeln!(
+                f,
+                "Limit - {}: {}",
+                limit.getter,
+                format_amount(limit.value, token)
+            )?;
+        }
+        for getter in &self.blacklisted_by {
+            writeln!(f, "Blacklisted by {getter}")?;
+        }
+        write!(f, "Tradable: {}", self.is_tradable())
+    }
+}
+
+/// Buys the token through the pool with `amount` of the other token of the pool, transfers a
+/// tenth of the tokens bought to the recipient and sells the rest back, reporting the taxes
+/// taken and why any of these failed.
+///
+/// The checks run from the session caller, which is funded with the amount to spend. The limit
+/// and blacklist getters commonly exposed by the tokens are read as well.
+pub fn check_token<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    config: &TokenSafetyConfig,
+) -> Result<TokenSafetyReport> {
+    let account = session.caller();
+    let token = Erc20::new(config.token);
+
+    let base_token = get_base_token(session, config.token, config.pool)?;
+    let limits = read_limits(session, config.token);
+
+    // Step 1: Buy the token, funding the account with the amount to spend.
+    let buy = match swap(session, config, base_token, config.amount, true) {
+        Ok(result) => TradeCheck {
+            amount_in: result.amount_in,
+            amount_out: result.amount_out,
+            tax: result.output_tax,
+            error: None,
+        },
+        Err(error) => TradeCheck::failed(config.amount, reportable(error)?),
+    };
+
+    let mut report = TokenSafetyReport {
+        token: config.token,
+        base_token,
+        token_metadata: token.metadata(session).ok(),
+        base_token_metadata: Erc20::new(base_token).metadata(session).ok(),
+        pool: config.pool.address(),
+        account,
+        buy,
+        transfer: None,
+        sell: None,
+        limits,
+        blacklisted_by: vec![],
+    };
+    if !report.buy.is_success() {
+        return Ok(report);
+    }
+
+    report.blacklisted_by = read_blacklist(session, config.token, account);
+
+    // Step 2: Transfer part of the tokens to another account.
+    let bought = token.balance_of(account, session)?.decoded;
+    let amount_transferred = bought / U256::from(10);
+    report.transfer = Some(transfer(
+        session,
+        &token,
+        config.recipient,
+        amount_transferred,
+    )?);
+
+    // Step 3: Sell the remaining tokens back to the pool.
+    let amount_sold = token.balance_of(account, session)?.decoded;
+    report.sell = Some(
+        match swap(session, config, config.token, amount_sold, false) {
+            Ok(result) => TradeCheck {
+                amount_in: result.amount_in,
+                amount_out: result.amount_out,
+                tax: result.input_tax,
+                error: None,
+            },
+            Err(error) => TradeCheck::failed(amount_sold, reportable(error)?),
+        },
+    );
+
+    Ok(report)
+}
+
+/// Checks that the pool is genuine for the factories of `chain` and returns the factory that
+/// deployed it, see [`uniswap_v2::pair::verify_pair`] and [`uniswap_v3::pool::verify_pool`].
+pub fn verify_trading_pool<'a, DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    pool: TradingPool,
+    chain: &'a ChainConfig,
+) -> Result<&'a FactoryConfig> {
+    match pool {
+        TradingPool::UniswapV2(address) => {
+            let pool_data = uniswap_v2::contracts::Pool::new(address).get_pool_data(session)?;
+            uniswap_v2::pair::verify_pair(address, &pool_data, &chain.uniswap_v2_factories)
+        }
+        TradingPool::UniswapV3 {
+            pool: address,
+            simulator,
+        } => {
+            uniswap_v3::swap_via_pool::deploy_simulator(simulator, session.database())?;
+            let pool_data = uniswap_v3::contracts::Pool::new(address, simulator)
+                .get_pool_data(session)?
+                .decoded;
+            uniswap_v3::pool::verify_pool(address, &pool_data, &chain.uniswap_v3_factories)
+        }
+    }
+}
+
+/// The other token of the pool, which is used to buy `token`.
+pub fn get_base_token<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    token: Address,
+    pool: TradingPool,
+) -> Result<Address> {
+    let (token_0, token_1) = match pool {
+        TradingPool::UniswapV2(address) => {
+            let pool_data = uniswap_v2::contracts::Pool::new(address).get_pool_data(session)?;
+            (pool_data.token_0, pool_data.token_1)
+        }
+        TradingPool::UniswapV3 {
+            pool: address,
+            simulator,
+        } => {
+            uniswap_v3::swap_via_pool::deploy_simulator(simulator, session.database())?;
+            let pool_data = uniswap_v3::contracts::Pool::new(address, simulator)
+                .get_pool_data(session)?
+                .decoded;
+            (pool_data.token_0, pool_data.token_1)
+        }
+    };
+
+    if token_0 == token {
+        Ok(token_1)
+    } else if token_1 == token {
+        Ok(token_0)
+    } else {
+        Err(SimulationError::Config(format!(
+            "The pool {} does not trade the token {}",
+            pool.address(),
+            token
+        )))
+    }
+}
+
+fn swap<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    config: &TokenSafetyConfig,
+    token_in: Address,
+    amount: U256,
+    fund_caller: bool,
+) -> Result<SwapResult> {
+    match config.pool {
+        TradingPool::UniswapV2(pool) => {
+            let swap_configuration =
+                uniswap_v2::swap_via_pool::SwapViaPoolConfig::new(pool, token_in, amount)
+                    .with_fee(config.fee)
+                    .with_fund_caller(fund_caller);
+            uniswap_v2::swap_via_pool::simulate(session, &swap_configuration)
+        }
+        TradingPool::UniswapV3 { pool, simulator } => {
+            let swap_configuration = uniswap_v3::swap_via_pool::SwapViaPoolConfig::new(
+                pool, token_in, amount, simulator,
+            )
+            .with_fund_caller(fund_caller);
+            uniswap_v3::swap_via_pool::simulate(session, &swap_configuration)
+        }
+    }
+}
+
+fn transfer<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    token: &Erc20,
+    recipient: Address,
+    amount: U256,
+) -> Result<TradeCheck> {
+    let balance_before = token.balance_of(recipient, session)?.decoded;
+
+    if let Err(error) = token.transfer(recipient, amount, session) {
+        return Ok(TradeCheck::failed(amount, reportable(error)?));
+    }
+
+    let balance_after = token.balance_of(recipient, session)?.decoded;
+    let received = balance_after.saturating_sub(balance_before);
+
+    Ok(TradeCheck {
+        amount_in: amount,
+        amount_out: received,
+        tax: Some(TransferTax {
+            sent: amount,
+            received,
+        }),
+        error: None,
+    })
+}
+
+// Errors telling something about the token are reported in the checks, the others (e.g. the node
+// failing) abort them.
+fn reportable(error: SimulationError) -> Result<SimulationError> {
+    match error {
+        SimulationError::Rpc(_) | SimulationError::Config(_) => Err(error),
+        error => Ok(error),
+    }
+}
+
+fn read_limits<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    token: Address,
+) -> Vec<TokenLimit> {
+    LIMIT_GETTERS
+        .iter()
+        .filter_map(|getter| {
+            let value = call_getter(session, token, getter, None)?;
+
+            Some(TokenLimit {
+                getter: getter.to_string(),
+                value,
+            })
+        })
+        .collect()
+}
+
+fn read_blacklist<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    token: Address,
+    account: Address,
+) -> Vec<String> {
+    BLACKLIST_GETTERS
+        .iter()
+        .filter(|getter| {
+            call_getter(session, token, getter, Some(account.into_word()))
+                .is_some_and(|value| !value.is_zero())
+        })
+        .map(|getter| getter.to_string())
+        .collect()
+}
+
+// Calls the getter with the given signature, returning the first word of its output. `None` when
+// the token does not implement it.
+fn call_getter<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    token: Address,
+    signature: &str,
+    argument: Option<B256>,
+) -> Option<U256> {
+    let mut calldata = keccak256(signature)[..4].to_vec();
+    if let Some(argument) = argument {
+        calldata.extend_from_slice(argument.as_slice());
+    }
+
+    session
+        .call(token, Bytes::from(calldata), |output| {
+            Ok(output.get(..32).map(U256::from_be_slice))
+        })
+        .ok()
+        .and_then(|outcome| outcome.decoded)
+}
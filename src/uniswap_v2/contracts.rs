@@ -0,0 +1,290 @@
+use alloy_sol_types::{sol, SolCall, SolEvent, SolValue};
+use revm::primitives::{Address, Bytes, Log, U256};
+
+use crate::commons::errors::{Result, SimulationError};
+use crate::commons::helpers::SimulationDatabase;
+use crate::commons::results::SimulationOutcome;
+use crate::commons::session::SimulationSession;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IRouter {
+
+        #[derive(Debug, PartialEq, Eq)]
+        function swapExactTokensForTokens(uint amountIn, uint amountOutMin, address[] calldata path,  address to, uint deadline) external returns (uint[] memory amounts);
+
+        #[derive(Debug, PartialEq, Eq)]
+        function swapTokensForExactTokens(uint amountOut, uint amountInMax, address[] calldata path, address to, uint deadline) external returns (uint[] memory amounts);
+
+        #[derive(Debug, PartialEq, Eq)]
+        function getAmountsIn(uint amountOut, address[] calldata path) external view returns (uint[] memory amounts);
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IFactory {
+        #[derive(Debug, PartialEq, Eq)]
+        function getPair(address tokenA, address tokenB) external view returns (address pair);
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IPool {
+        #[derive(Debug)]
+        event Swap(address indexed sender, uint amount0In, uint amount1In, uint amount0Out, uint amount1Out, address indexed to);
+
+        #[derive(Debug, PartialEq, Eq)]
+        function token0() external view returns (address);
+
+        #[derive(Debug, PartialEq, Eq)]
+        function token1() external view returns (address);
+
+        #[derive(Debug, PartialEq, Eq)]
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+
+        #[derive(Debug, PartialEq, Eq)]
+        function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes calldata data) external;
+
+        #[derive(Debug, PartialEq, Eq)]
+        function factory() external view returns (address);
+    }
+}
+
+#[derive(Debug)]
+pub struct Router {
+    router: Address,
+}
+
+impl Router {
+    pub fn new(router: Address) -> Self {
+        Self { router }
+    }
+
+    pub fn swap_exact_tokens_for_tokens<DB: SimulationDatabase>(
+        &self,
+        amount_in: U256,
+        amount_out_min: U256,
+        path: Vec<Address>,
+        to: Address,
+        deadline: U256,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<Vec<U256>>> {
+        let calldata = Bytes::from(
+            IRouter::swapExactTokensForTokensCall::new((
+                amount_in,
+                amount_out_min,
+                path,
+                to,
+                deadline,
+            ))
+            .abi_encode(),
+        );
+
+        session.execute(self.router, calldata, |output| {
+            Ok(<Vec<U256>>::abi_decode(output, false)?)
+        })
+    }
+
+    pub fn swap_tokens_for_exact_tokens<DB: SimulationDatabase>(
+        &self,
+        amount_out: U256,
+        amount_in_max: U256,
+        path: Vec<Address>,
+        to: Address,
+        deadline: U256,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<Vec<U256>>> {
+        let calldata = Bytes::from(
+            IRouter::swapTokensForExactTokensCall::new((
+                amount_out,
+                amount_in_max,
+                path,
+                to,
+                deadline,
+            ))
+            .abi_encode(),
+        );
+
+        session.execute(self.router, calldata, |output| {
+            Ok(<Vec<U256>>::abi_decode(output, false)?)
+        })
+    }
+
+    /// The input amounts needed along `path` to receive `amount_out` of its last token, the
+    /// first one being what the caller pays.
+    pub fn get_amounts_in<DB: SimulationDatabase>(
+        &self,
+        amount_out: U256,
+        path: Vec<Address>,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<Vec<U256>>> {
+        let calldata = Bytes::from(IRouter::getAmountsInCall::new((amount_out, path)).abi_encode());
+
+        session.call(self.router, calldata, |output| {
+            Ok(<Vec<U256>>::abi_decode(output, false)?)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Factory {
+    factory: Address,
+}
+
+impl Factory {
+    pub fn new(factory: Address) -> Self {
+        Self { factory }
+    }
+
+    /// The pair of `token_a` and `token_b` deployed by the factory, `None` when there is none.
+    pub fn get_pair<DB: SimulationDatabase>(
+        &self,
+        token_a: Address,
+        token_b: Address,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<Option<Address>> {
+        let calldata = Bytes::from(IFactory::getPairCall::new((token_a, token_b)).abi_encode());
+
+        let outcome = session.call(self.factory, calldata, |output| {
+            Ok(<Address>::abi_decode(output, true)?)
+        })?;
+
+        Ok(Some(outcome.decoded).filter(|pair| !pair.is_zero()))
+    }
+}
+
+#[derive(Debug)]
+pub struct Pool {
+    pool: Address,
+}
+
+#[derive(Debug, Clone)]
+pub struct Swap {
+    pub pool: Address,
+    pub amount0_in: U256,
+    pub amount1_in: U256,
+    pub amount0_out: U256,
+    pub amount1_out: U256,
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolData {
+    pub token_0: Address,
+    pub token_1: Address,
+    /// The factory the pool claims to be deployed by, `None` when it does not implement
+    /// `factory`.
+    pub factory: Option<Address>,
+}
+
+impl Pool {
+    pub fn new(pool: Address) -> Self {
+        Self { pool }
+    }
+
+    pub fn decode_swaps(logs: &[Log]) -> Result<Vec<Swap>> {
+        let mut swaps = vec![];
+
+        for log in logs.iter() {
+            if !log.data.topics().is_empty() && log.data.topics()[0] == IPool::Swap::SIGNATURE_HASH
+            {
+                if let Ok(swap) = IPool::Swap::decode_log(log, true) {
+                    swaps.push(Swap {
+                        pool: log.address,
+                        amount0_in: swap.amount0In,
+                        amount1_in: swap.amount1In,
+                        amount0_out: swap.amount0Out,
+                        amount1_out: swap.amount1Out,
+                    });
+                }
+            }
+        }
+
+        Ok(swaps)
+    }
+
+    pub fn swap<DB: SimulationDatabase>(
+        &self,
+        amount0_out: U256,
+        amount1_out: U256,
+        to: Address,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<()>> {
+        let calldata = Bytes::from(
+            IPool::swapCall::new((amount0_out, amount1_out, to, Bytes::default())).abi_encode(),
+        );
+
+        session.execute(self.pool, calldata, |_| Ok(()))
+    }
+
+    pub fn get_reserves<DB: SimulationDatabase>(
+        &self,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<(U256, U256)>> {
+        let calldata = Bytes::from(IPool::getReservesCall::new(()).abi_encode());
+
+        session.call(self.pool, calldata, |output| {
+            let (reserve_0, reserve_1, _) = <(U256, U256, u128)>::abi_decode(output, true)?;
+            Ok((reserve_0, reserve_1))
+        })
+    }
+
+    pub fn get_pool_data<DB: SimulationDatabase>(
+        &self,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<PoolData> {
+        let token_0 = self.get_token_0(session)?;
+        let token_1 = self.get_token_1(session)?;
+        let factory = match self.get_factory(session) {
+            Ok(factory) => Some(factory),
+            Err(error @ SimulationError::Rpc(_)) => return Err(error),
+            Err(_) => None,
+        };
+
+        Ok(PoolData {
+            token_0,
+            token_1,
+            factory,
+        })
+    }
+
+    fn get_factory<DB: SimulationDatabase>(
+        &self,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<Address> {
+        let calldata = Bytes::from(IPool::factoryCall::new(()).abi_encode());
+        self.get_address(calldata, session)
+    }
+
+    fn get_token_0<DB: SimulationDatabase>(
+        &self,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<Address> {
+        let calldata = Bytes::from(IPool::token0Call::new(()).abi_encode());
+        self.get_address(calldata, session)
+    }
+
+    fn get_token_1<DB: SimulationDatabase>(
+        &self,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<Address> {
+        let calldata = Bytes::from(IPool::token1Call::new(()).abi_encode());
+        self.get_address(calldata, session)
+    }
+
+    fn get_address<DB: SimulationDatabase>(
+        &self,
+        calldata: Bytes,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<Address> {
+        let outcome = session.call(self.pool, calldata, |output| {
+            Ok(<Address>::abi_decode(output, true)?)
+        })?;
+
+        Ok(outcome.decoded)
+    }
+}
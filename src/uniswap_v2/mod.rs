@@ -0,0 +1,5 @@
+pub mod contracts;
+pub mod pair;
+pub mod swap_via_pool;
+pub mod swap_via_router;
+pub mod v2_math;
@@ -0,0 +1,103 @@
+use revm::primitives::{keccak256, Address};
+
+use crate::commons::errors::{Result, SimulationError};
+use crate::commons::helpers::SimulationDatabase;
+use crate::commons::registry::FactoryConfig;
+use crate::commons::session::SimulationSession;
+
+use super::contracts::{Factory, PoolData};
+
+/// Orders the tokens the way pairs do, by address.
+pub fn sort_tokens(token_a: Address, token_b: Address) -> Result<(Address, Address)> {
+    if token_a == token_b {
+        return Err(SimulationError::Config(format!(
+            "A pair needs two different tokens, got {token_a} twice"
+        )));
+    }
+
+    Ok(if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    })
+}
+
+/// Computes offline the address the factory deploys the pair of `token_a` and `token_b` at, with
+/// `CREATE2` salted by the sorted tokens.
+///
+/// For more information check the contract code at: https://github.com/Uniswap/v2-periphery/blob/0335e8f7e1bd1e8d8329fd300aea2ef2f36dd19f/contracts/libraries/UniswapV2Library.sol#L18
+pub fn compute_pair_address(
+    factory: &FactoryConfig,
+    token_a: Address,
+    token_b: Address,
+) -> Result<Address> {
+    let (token_0, token_1) = sort_tokens(token_a, token_b)?;
+    let salt = keccak256([token_0.as_slice(), token_1.as_slice()].concat());
+
+    Ok(factory.address.create2(salt, factory.init_code_hash))
+}
+
+/// Finds the pair of `token_a` and `token_b` deployed by `factory` in the fork.
+///
+/// The address computed offline is used when there is code at it, which saves a call. Otherwise
+/// the factory is asked with `getPair`, which also covers factories configured with a wrong init
+/// code hash.
+pub fn find_pair<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    factory: &FactoryConfig,
+    token_a: Address,
+    token_b: Address,
+) -> Result<Address> {
+    let pair = compute_pair_address(factory, token_a, token_b)?;
+    let deployed = session
+        .database()
+        .basic(pair)
+        .map_err(SimulationError::rpc)?
+        .is_some_and(|account| !account.is_empty_code_hash());
+    if deployed {
+        return Ok(pair);
+    }
+
+    Factory::new(factory.address)
+        .get_pair(token_a, token_b, session)?
+        .ok_or_else(|| {
+            SimulationError::Config(format!(
+                "There is no {} pair for {token_a} and {token_b}",
+                factory.name
+            ))
+        })
+}
+
+/// Checks that `pool` is a genuine pair: its factory is one of `factories` and the pool lives at
+/// the address the factory deploys the pair of its tokens at. A malicious contract can pretend to
+/// be a pair, but cannot be deployed at that address. Returns the factory of the pool.
+pub fn verify_pair<'a>(
+    pool: Address,
+    pool_data: &PoolData,
+    factories: &'a [FactoryConfig],
+) -> Result<&'a FactoryConfig> {
+    let Some(factory_address) = pool_data.factory else {
+        return Err(SimulationError::Config(format!(
+            "The pool {pool} does not tell which factory deployed it"
+        )));
+    };
+
+    let factory = factories
+        .iter()
+        .find(|factory| factory.address == factory_address)
+        .ok_or_else(|| {
+            SimulationError::Config(format!(
+                "The pool {pool} claims to be deployed by {factory_address}, which is not a known Uniswap V2 factory"
+            ))
+        })?;
+
+    let expected = compute_pair_address(factory, pool_data.token_0, pool_data.token_1)?;
+    if expected != pool {
+        return Err(SimulationError::Config(format!(
+            "The pool {pool} is not the {} pair of {} and {}, which lives at {expected}",
+            factory.name, pool_data.token_0, pool_data.token_1
+        )));
+    }
+
+    Ok(factory)
+}
@@ -0,0 +1,267 @@
+use revm::primitives::{Address, U256};
+
+use crate::commons::erc20::Erc20;
+use crate::commons::errors::{Result, SimulationError};
+use crate::commons::helpers::{fund_caller, SimulationDatabase};
+use crate::commons::results::{SwapResult, TransferTax};
+use crate::commons::session::SimulationSession;
+
+use super::contracts::{Pool, PoolData};
+use super::v2_math::{get_amount_in, get_amount_out, SwapFee};
+
+#[derive(Debug, Clone)]
+pub struct SwapViaPoolConfig {
+    pub pool: Address,
+    pub token_in: Address,
+    pub amount: U256,
+    /// Fee taken by the pool, which depends on the DEX that deployed it.
+    pub fee: SwapFee,
+    /// Whether `amount` is the amount of token out to receive instead of the amount of token in
+    /// to swap.
+    pub exact_out: bool,
+    /// Whether the caller is given the input amount (and some ether) before swapping.
+    pub fund_caller: bool,
+}
+
+impl SwapViaPoolConfig {
+    pub fn new(pool: Address, token_in: Address, amount: U256) -> Self {
+        Self {
+            pool,
+            token_in,
+            amount,
+            fee: SwapFee::default(),
+            exact_out: false,
+            fund_caller: true,
+        }
+    }
+
+    pub fn with_fee(mut self, fee: SwapFee) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    pub fn with_exact_out(mut self, exact_out: bool) -> Self {
+        self.exact_out = exact_out;
+        self
+    }
+
+    pub fn with_fund_caller(mut self, fund_caller: bool) -> Self {
+        self.fund_caller = fund_caller;
+        self
+    }
+}
+
+/// Swaps `amount` of `token_in` hitting the pool straight away, computing the output amounts
+/// from the pool reserves. In exact output, `amount` of token out is bought for the input
+/// computed by [`get_input_amount`].
+pub fn simulate<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    swap_configuration: &SwapViaPoolConfig,
+) -> Result<SwapResult> {
+    let caller = session.caller();
+
+    let pool = Pool::new(swap_configuration.pool);
+    let pool_data = pool.get_pool_data(session)?;
+
+    let token_in = Erc20::new(swap_configuration.token_in);
+    let token_out = Erc20::new(other_token(
+        swap_configuration.pool,
+        &pool_data,
+        swap_configuration.token_in,
+    )?);
+
+    let amount_in = if swap_configuration.exact_out {
+        get_input_amount(
+            swap_configuration.pool,
+            swap_configuration.token_in,
+            swap_configuration.amount,
+            swap_configuration.fee,
+            session,
+        )?
+    } else {
+        swap_configuration.amount
+    };
+
+    if swap_configuration.fund_caller {
+        fund_caller(session, &token_in, amount_in)?;
+    }
+
+    // Save the balances before perdorming the swap so that we can validate that the swap
+    // was successful.
+    let balance_in_before = token_in.balance_of(caller, session)?.decoded;
+    let balance_out_before = token_out.balance_of(caller, session)?.decoded;
+    let pool_balance_before = token_in
+        .balance_of(swap_configuration.pool, session)?
+        .decoded;
+
+    // Mandatory step, before executing the swap on the pool we need to transfer the correct input
+    // token amount to the pool. Without this step, the swap will fail with a `ISUFFICIENT
+    // LIQUIDITY` error.
+    let transfer = token_in.transfer(swap_configuration.pool, amount_in, session)?;
+
+    // Tokens taking a fee on transfer deliver less than `amount` to the pool. The pool computes
+    // the input from its balance minus its reserve, so the outputs must be computed the same way,
+    // otherwise the swap fails with a `K` error.
+    let (reserve_0, reserve_1) = pool.get_reserves(session)?.decoded;
+    let reserve_in = if pool_data.token_0 == swap_configuration.token_in {
+        reserve_0
+    } else {
+        reserve_1
+    };
+    let pool_balance_after = token_in
+        .balance_of(swap_configuration.pool, session)?
+        .decoded;
+    let amount_received = pool_balance_after.checked_sub(reserve_in).ok_or_else(|| {
+        SimulationError::InvariantViolation(format!(
+            "The pool {} holds less than its reserve of token in",
+            swap_configuration.pool
+        ))
+    })?;
+    let input_tax = TransferTax {
+        sent: amount_in,
+        received: pool_balance_after.saturating_sub(pool_balance_before),
+    };
+
+    let (amount0_out, amount1_out) = if swap_configuration.exact_out {
+        // The input was computed for the pool to receive all of it.
+        if amount_received < amount_in {
+            return Err(SimulationError::InvariantViolation(format!(
+                "{} takes a fee on transfer, the pool received {amount_received} instead of {amount_in} to buy an exact output",
+                swap_configuration.token_in
+            )));
+        }
+
+        if pool_data.token_0 == swap_configuration.token_in {
+            (U256::ZERO, swap_configuration.amount)
+        } else {
+            (swap_configuration.amount, U256::ZERO)
+        }
+    } else {
+        get_output_amounts(
+            swap_configuration.pool,
+            swap_configuration.token_in,
+            amount_received,
+            swap_configuration.fee,
+            session,
+        )?
+    };
+
+    let swap = pool.swap(amount0_out, amount1_out, caller, session)?;
+
+    // In order to ensure the swap was successful, we need to check the balances of both tokens
+    // before and after the swap.
+    let balance_in_after = token_in.balance_of(caller, session)?.decoded;
+    let balance_out_after = token_out.balance_of(caller, session)?.decoded;
+
+    let expected_amount_out = if pool_data.token_0 == swap_configuration.token_in {
+        amount1_out
+    } else {
+        amount0_out
+    };
+
+    if balance_in_before.checked_sub(amount_in) != Some(balance_in_after) {
+        return Err(SimulationError::InvariantViolation(
+            "The balance of token in does not match the expected output".to_owned(),
+        ));
+    }
+    // The output token may take a fee as well, hence less than the pool sent is received.
+    let amount_out = balance_out_after
+        .checked_sub(balance_out_before)
+        .filter(|amount_out| *amount_out <= expected_amount_out)
+        .ok_or_else(|| {
+            SimulationError::InvariantViolation(
+                "The balance of token out does not match the expected output".to_owned(),
+            )
+        })?;
+    let output_tax = TransferTax {
+        sent: expected_amount_out,
+        received: amount_out,
+    };
+
+    Ok(SwapResult {
+        pool: swap_configuration.pool,
+        token_in: swap_configuration.token_in,
+        token_out: token_out.address(),
+        amount_in,
+        amount_out,
+        steps: vec![
+            transfer.into_step("ERC20 Transfer"),
+            swap.into_step("Pool Swap"),
+        ],
+        token_in_metadata: token_in.metadata(session).ok(),
+        token_out_metadata: token_out.metadata(session).ok(),
+        input_tax: Some(input_tax),
+        output_tax: Some(output_tax),
+        route: vec![],
+        gas_saved: None,
+        transactions: vec![],
+        bundles: vec![],
+        inclusion: None,
+    })
+}
+
+/// Computes the `(amount0_out, amount1_out)` arguments expected by the pool `swap` when it
+/// receives `amount_in` of `token_in`, which is less than the amount sent for fee-on-transfer
+/// tokens.
+pub fn get_output_amounts<DB: SimulationDatabase>(
+    pool: Address,
+    token_in: Address,
+    amount_in: U256,
+    fee: SwapFee,
+    session: &mut SimulationSession<DB>,
+) -> Result<(U256, U256)> {
+    let (reserve_in, reserve_out, token_in_is_0) = get_reserves(pool, token_in, session)?;
+
+    let amount_out = get_amount_out(amount_in, reserve_in, reserve_out, fee)?;
+
+    if token_in_is_0 {
+        Ok((U256::ZERO, amount_out))
+    } else {
+        Ok((amount_out, U256::ZERO))
+    }
+}
+
+/// Computes the amount of `token_in` the pool must receive to send `amount_out` of the other
+/// token.
+pub fn get_input_amount<DB: SimulationDatabase>(
+    pool: Address,
+    token_in: Address,
+    amount_out: U256,
+    fee: SwapFee,
+    session: &mut SimulationSession<DB>,
+) -> Result<U256> {
+    let (reserve_in, reserve_out, _) = get_reserves(pool, token_in, session)?;
+
+    get_amount_in(amount_out, reserve_in, reserve_out, fee)
+}
+
+// The reserves of token in and token out, and whether token in is `token0`.
+fn get_reserves<DB: SimulationDatabase>(
+    pool: Address,
+    token_in: Address,
+    session: &mut SimulationSession<DB>,
+) -> Result<(U256, U256, bool)> {
+    let contract = Pool::new(pool);
+
+    let (reserve_0, reserve_1) = contract.get_reserves(session)?.decoded;
+    let pool_data = contract.get_pool_data(session)?;
+
+    if other_token(pool, &pool_data, token_in)? == pool_data.token_1 {
+        Ok((reserve_0, reserve_1, true))
+    } else {
+        Ok((reserve_1, reserve_0, false))
+    }
+}
+
+// The token of the pair `token_in` is swapped for.
+fn other_token(pool: Address, pool_data: &PoolData, token_in: Address) -> Result<Address> {
+    if token_in == pool_data.token_0 {
+        Ok(pool_data.token_1)
+    } else if token_in == pool_data.token_1 {
+        Ok(pool_data.token_0)
+    } else {
+        Err(SimulationError::Config(format!(
+            "The pool {pool} does not trade the token {token_in}"
+        )))
+    }
+}
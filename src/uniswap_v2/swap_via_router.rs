@@ -0,0 +1,291 @@
+use revm::primitives::{Address, U256};
+
+use crate::commons::erc20::Erc20;
+use crate::commons::errors::{Result, SimulationError};
+use crate::commons::helpers::{fund_caller, SimulationDatabase};
+use crate::commons::results::{SimulationOutcome, SwapResult};
+use crate::commons::session::SimulationSession;
+
+use super::contracts::{Pool, Router};
+
+#[derive(Debug, Clone)]
+pub struct SwapViaRouterConfig {
+    pub router: Address,
+    pub token_in: Address,
+    pub token_out: Address,
+    /// Tokens the router swaps through between token in and token out, in order.
+    pub via: Vec<Address>,
+    pub amount: U256,
+    /// Whether `amount` is the amount of token out to receive instead of the amount of token in
+    /// to swap.
+    pub exact_out: bool,
+    /// Whether the caller is given the input amount (and some ether) before swapping.
+    pub fund_caller: bool,
+    /// Whether the allowance of the router is written in the token storage instead of executing
+    /// an approval before swapping.
+    pub override_allowance: bool,
+}
+
+impl SwapViaRouterConfig {
+    pub fn new(router: Address, token_in: Address, token_out: Address, amount: U256) -> Self {
+        Self {
+            router,
+            token_in,
+            token_out,
+            via: vec![],
+            amount,
+            exact_out: false,
+            fund_caller: true,
+            override_allowance: false,
+        }
+    }
+
+    pub fn with_via(mut self, via: Vec<Address>) -> Self {
+        self.via = via;
+        self
+    }
+
+    /// The path given to the router, from token in to token out.
+    pub fn path(&self) -> Vec<Address> {
+        let mut path = vec![self.token_in];
+        path.extend(&self.via);
+        path.push(self.token_out);
+        path
+    }
+
+    pub fn with_exact_out(mut self, exact_out: bool) -> Self {
+        self.exact_out = exact_out;
+        self
+    }
+
+    pub fn with_fund_caller(mut self, fund_caller: bool) -> Self {
+        self.fund_caller = fund_caller;
+        self
+    }
+
+    pub fn with_override_allowance(mut self, override_allowance: bool) -> Self {
+        self.override_allowance = override_allowance;
+        self
+    }
+}
+
+/// The swap via the router, along with the approvals it needed.
+#[derive(Debug, Clone)]
+pub struct RouterSwap {
+    /// Empty when the allowance is overridden.
+    pub approvals: Vec<SimulationOutcome<bool>>,
+    /// Its logs contain the Swap events used to discover the pools and the amounts.
+    pub swap: SimulationOutcome<Vec<U256>>,
+}
+
+impl RouterSwap {
+    /// Gas used by the approvals and the swap.
+    pub fn gas_used(&self) -> u64 {
+        self.approvals
+            .iter()
+            .map(|approval| approval.gas.gas_used)
+            .sum::<u64>()
+            + self.swap.gas.gas_used
+    }
+}
+
+/// Simulates the swap via the Uniswap V2 Router, with `swapTokensForExactTokens` in exact output,
+/// after approving the router to spend the input amount.
+pub fn simulate_with_router<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    swap_configuration: &SwapViaRouterConfig,
+) -> Result<RouterSwap> {
+    let caller = session.caller();
+
+    let token = Erc20::new(swap_configuration.token_in);
+    let router = Router::new(swap_configuration.router);
+
+    let path = swap_configuration.path();
+
+    // When buying an exact amount of token out, the router tells how much token in it takes,
+    // which is what the caller is funded with and allows the router to spend.
+    let amount_in = if swap_configuration.exact_out {
+        let amounts = router
+            .get_amounts_in(swap_configuration.amount, path.clone(), session)?
+            .decoded;
+        amounts.first().copied().ok_or_else(|| {
+            SimulationError::Decoding("The router returned no input amount".to_owned())
+        })?
+    } else {
+        swap_configuration.amount
+    };
+
+    if swap_configuration.fund_caller {
+        fund_caller(session, &token, amount_in)?;
+    }
+
+    // Mandatory step, the router performs a `transfer_from` call pon the Erc20 on behalf of the
+    // caller. Without this step, the swap will fail with a `TRANSFER_FROM_FAILED` error. The
+    // allowance can also be written directly, skipping the approval transaction.
+    let approvals = if swap_configuration.override_allowance {
+        token.set_allowance(caller, swap_configuration.router, amount_in, session)?;
+        vec![]
+    } else {
+        token.force_approve(swap_configuration.router, amount_in, session)?
+    };
+
+    // The amount_out_min indicates the router the minimum amount of output tokens expected.
+    // The router will fail the swap if the output amount is smaller than this. For our purposes
+    // we set it to zero as we are only running a simulation.
+    let amount_out_min = U256::ZERO;
+
+    // The deadline, expressed in timestamp, indicates the number of blocks allowed to process the
+    // swap. Since there is a new block produced each 12 seconds, we are specifying that the swap
+    // is only valid for the next 3 blocks.
+    let deadline = U256::from(session.block().header.timestamp + 36);
+
+    let swap = if swap_configuration.exact_out {
+        router.swap_tokens_for_exact_tokens(
+            swap_configuration.amount,
+            amount_in,
+            path,
+            caller,
+            deadline,
+            session,
+        )?
+    } else {
+        router.swap_exact_tokens_for_tokens(
+            swap_configuration.amount,
+            amount_out_min,
+            path,
+            caller,
+            deadline,
+            session,
+        )?
+    };
+
+    Ok(RouterSwap { approvals, swap })
+}
+
+/// Replays the swap discovered by [`simulate_with_router`] directly against the pools.
+///
+/// Every hop comes from a Swap event of the router swap. The input is transferred once to the
+/// first pool, and each pool sends its output straight to the next one, as the router does, the
+/// last one sending it to the caller.
+///
+/// The gas saved is compared with the approvals and the swap of the router, as the pools need no
+/// approval. The approval is not accounted when the allowance is overridden.
+pub fn simulate_with_pool<DB: SimulationDatabase>(
+    session: &mut SimulationSession<DB>,
+    router_swap: &RouterSwap,
+    swap_configuration: &SwapViaRouterConfig,
+) -> Result<SwapResult> {
+    let caller = session.caller();
+
+    let swap_events = Pool::decode_swaps(&router_swap.swap.logs)?;
+
+    let (Some(first_swap), Some(last_swap)) = (swap_events.first(), swap_events.last()) else {
+        return Err(SimulationError::InvariantViolation(
+            "The router swap emitted no Swap event".to_owned(),
+        ));
+    };
+
+    let token_in = Erc20::new(swap_configuration.token_in);
+    let token_out = Erc20::new(swap_configuration.token_out);
+
+    // Pools have `token0` and `token1`, we are not analyzing the pool, which means we don't
+    // know which token we are swapping for. An alternative to get that information without
+    // analyzing the pool is to check the Swap event and see which input amount (`amount0_in` or
+    // `amount1_in`) is not zero.
+    let amount_in = if first_swap.amount0_in.is_zero() {
+        first_swap.amount1_in
+    } else {
+        first_swap.amount0_in
+    };
+    let amount_out = if last_swap.amount0_out.is_zero() {
+        last_swap.amount1_out
+    } else {
+        last_swap.amount0_out
+    };
+
+    // The amount in comes from the router swap, as `amount` is the amount out in exact output.
+    if swap_configuration.fund_caller {
+        fund_caller(session, &token_in, amount_in)?;
+    }
+
+    // Save the balances before perdorming the swap so that we can validate that the swap
+    // was successful.
+    let balance_in_before = token_in.balance_of(caller, session)?.decoded;
+    let balance_out_before = token_out.balance_of(caller, session)?.decoded;
+
+    // Mandatory step, before executing the swap on the pool we need to transfer the correct input
+    // token amount to the pool. Without this step, the swap will fail with a `IIA` (Insufficient
+    // Input Amount) error.
+    let transfer = token_in.transfer(first_swap.pool, amount_in, session)?;
+
+    let mut steps = vec![transfer.into_step("ERC20 Transfer")];
+    for (hop, swap_event) in swap_events.iter().enumerate() {
+        let to = swap_events
+            .get(hop + 1)
+            .map_or(caller, |next_swap| next_swap.pool);
+
+        let swap = Pool::new(swap_event.pool).swap(
+            swap_event.amount0_out,
+            swap_event.amount1_out,
+            to,
+            session,
+        )?;
+
+        let name = if swap_events.len() == 1 {
+            "Pool Swap".to_owned()
+        } else {
+            format!("Pool Swap {}", hop + 1)
+        };
+        steps.push(swap.into_step(&name));
+    }
+
+    // In order to ensure the swap was successful, we need to check the balances of both tokens
+    // before and after the swap.
+    let balance_in_after = token_in.balance_of(caller, session)?.decoded;
+    let balance_out_after = token_out.balance_of(caller, session)?.decoded;
+
+    if balance_in_before.checked_sub(amount_in) != Some(balance_in_after) {
+        return Err(SimulationError::InvariantViolation(
+            "The balance of token in does not match the expected output".to_owned(),
+        ));
+    }
+    if balance_out_before.checked_add(amount_out) != Some(balance_out_after) {
+        return Err(SimulationError::InvariantViolation(
+            "The balance of token out does not match the expected output".to_owned(),
+        ));
+    }
+
+    let gas_used = steps
+        .iter()
+        .map(|step| step.outcome.gas.gas_used)
+        .sum::<u64>();
+    let gas_saved = i64::try_from(router_swap.gas_used()).unwrap_or(i64::MAX)
+        - i64::try_from(gas_used).unwrap_or(i64::MAX);
+
+    let route = if swap_events.len() > 1 {
+        swap_events
+            .iter()
+            .map(|swap_event| swap_event.pool)
+            .collect()
+    } else {
+        vec![]
+    };
+
+    Ok(SwapResult {
+        pool: first_swap.pool,
+        token_in: swap_configuration.token_in,
+        token_out: swap_configuration.token_out,
+        amount_in,
+        amount_out,
+        steps,
+        token_in_metadata: token_in.metadata(session).ok(),
+        token_out_metadata: token_out.metadata(session).ok(),
+        input_tax: None,
+        output_tax: None,
+        route,
+        gas_saved: Some(gas_saved),
+        transactions: vec![],
+        bundles: vec![],
+        inclusion: None,
+    })
+}
@@ -0,0 +1,117 @@
+use revm::primitives::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::commons::errors::{Result, SimulationError};
+
+/// Fee taken by a pair on the input, as `numerator / denominator`: 3/1000 for Uniswap and
+/// SushiSwap, 25/10000 for PancakeSwap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapFee {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl Default for SwapFee {
+    fn default() -> Self {
+        Self::UNISWAP
+    }
+}
+
+impl SwapFee {
+    pub const UNISWAP: Self = Self {
+        numerator: 3,
+        denominator: 1000,
+    };
+
+    pub fn new(numerator: u64, denominator: u64) -> Result<Self> {
+        if numerator >= denominator {
+            return Err(SimulationError::Config(format!(
+                "Invalid swap fee {numerator}/{denominator}, it must be below 100%"
+            )));
+        }
+
+        Ok(Self {
+            numerator,
+            denominator,
+        })
+    }
+
+    // The part of the input left once the fee is taken, e.g. 997 of 1000.
+    fn remainder(&self) -> Result<U256> {
+        self.denominator
+            .checked_sub(self.numerator)
+            .filter(|remainder| *remainder > 0)
+            .map(U256::from)
+            .ok_or_else(|| {
+                SimulationError::Config(format!(
+                    "Invalid swap fee {}/{}, it must be below 100%",
+                    self.numerator, self.denominator
+                ))
+            })
+    }
+}
+
+/// The output of a pair receiving `amount_in`, as `getAmountOut` of the Uniswap V2 library.
+///
+/// For more information check the contract code at: https://github.com/Uniswap/v2-periphery/blob/0335e8f7e1bd1e8d8329fd300aea2ef2f36dd19f/contracts/libraries/UniswapV2Library.sol#L43
+pub fn get_amount_out(
+    amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee: SwapFee,
+) -> Result<U256> {
+    if amount_in.is_zero() {
+        return Err(math_error("INSUFFICIENT_INPUT_AMOUNT"));
+    }
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(math_error("INSUFFICIENT_LIQUIDITY"));
+    }
+
+    let amount_in_with_fee = checked_mul(amount_in, fee.remainder()?)?;
+    let numerator = checked_mul(amount_in_with_fee, reserve_out)?;
+    let denominator = checked_mul(reserve_in, U256::from(fee.denominator))?
+        .checked_add(amount_in_with_fee)
+        .ok_or_else(overflow)?;
+
+    Ok(numerator / denominator)
+}
+
+/// The input a pair must receive to send `amount_out`, as `getAmountIn` of the Uniswap V2
+/// library. Rounded up, so the pair always receives enough.
+///
+/// For more information check the contract code at: https://github.com/Uniswap/v2-periphery/blob/0335e8f7e1bd1e8d8329fd300aea2ef2f36dd19f/contracts/libraries/UniswapV2Library.sol#L53
+pub fn get_amount_in(
+    amount_out: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee: SwapFee,
+) -> Result<U256> {
+    if amount_out.is_zero() {
+        return Err(math_error("INSUFFICIENT_OUTPUT_AMOUNT"));
+    }
+    if reserve_in.is_zero() || amount_out >= reserve_out {
+        return Err(math_error("INSUFFICIENT_LIQUIDITY"));
+    }
+
+    let numerator = checked_mul(
+        checked_mul(reserve_in, amount_out)?,
+        U256::from(fee.denominator),
+    )?;
+    let denominator = checked_mul(reserve_out - amount_out, fee.remainder()?)?;
+
+    (numerator / denominator)
+        .checked_add(U256::from(1))
+        .ok_or_else(overflow)
+}
+
+fn checked_mul(a: U256, b: U256) -> Result<U256> {
+    a.checked_mul(b).ok_or_else(overflow)
+}
+
+fn overflow() -> SimulationError {
+    SimulationError::InvariantViolation("Overflow in the Uniswap V2 math".to_owned())
+}
+
+fn math_error(reason: &str) -> SimulationError {
+    SimulationError::InvariantViolation(format!("UniswapV2Library: {reason}"))
+}
@@ -0,0 +1,125 @@
+use alloy_sol_types::{sol, SolCall, SolEvent, SolValue};
+use revm::primitives::{Address, Bytes, Log, I256, U256};
+use serde::Serialize;
+
+use crate::commons::errors::Result;
+use crate::commons::helpers::SimulationDatabase;
+use crate::commons::results::SimulationOutcome;
+use crate::commons::session::SimulationSession;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IPool {
+        #[derive(Debug)]
+        event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick);
+
+        #[derive(Debug, PartialEq, Eq)]
+        function swap(address poolAddress, address recipient, address tokenIn, address tokenOut, bool zeroForOne, uint256 amountIn) external returns ( uint256 tokenInBalanceBefore, uint256 tokenInBalanceAfter, uint256 tokenOutBalanceBefore, uint256 tokenOutBalanceAfter);
+
+        #[derive(Debug, PartialEq, Eq)]
+        function getPoolData(address pool) external view returns (address token00, address token01, address factory, uint24 fee);
+    }
+}
+
+#[derive(Debug)]
+pub struct Pool {
+    pool: Address,
+    simulator: Address,
+}
+
+#[derive(Debug, Clone)]
+pub struct Swap {
+    pub pool: Address,
+    pub amount_0: I256,
+    pub amount_1: I256,
+}
+
+/// Balances of the recipient reported by the simulator around the swap.
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapBalances {
+    pub token_in_balance_before: U256,
+    pub token_in_balance_after: U256,
+    pub token_out_balance_before: U256,
+    pub token_out_balance_after: U256,
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolData {
+    /// The factory the pool claims to be deployed by.
+    pub factory: Address,
+    pub token_0: Address,
+    pub token_1: Address,
+    /// The fee tier, in hundredths of a basis point.
+    pub fee: u128,
+}
+
+impl Pool {
+    pub fn new(pool: Address, simulator: Address) -> Self {
+        Self { pool, simulator }
+    }
+
+    pub fn decode_swaps(logs: &[Log]) -> Result<Vec<Swap>> {
+        let mut swaps = vec![];
+
+        for log in logs.iter() {
+            if !log.data.topics().is_empty() && log.data.topics()[0] == IPool::Swap::SIGNATURE_HASH
+            {
+                if let Ok(swap) = IPool::Swap::decode_log(log, true) {
+                    swaps.push(Swap {
+                        pool: log.address,
+                        amount_0: swap.amount0,
+                        amount_1: swap.amount1,
+                    });
+                }
+            }
+        }
+
+        Ok(swaps)
+    }
+
+    pub fn swap<DB: SimulationDatabase>(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        zero_for_one: bool,
+        amount_in: U256,
+        to: Address,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<SwapBalances>> {
+        let calldata = Bytes::from(
+            IPool::swapCall::new((self.pool, to, token_in, token_out, zero_for_one, amount_in))
+                .abi_encode(),
+        );
+
+        session.execute(self.simulator, calldata, |output| {
+            let result = IPool::swapCall::abi_decode_returns(output, true)?;
+
+            Ok(SwapBalances {
+                token_in_balance_before: result.tokenInBalanceBefore,
+                token_in_balance_after: result.tokenInBalanceAfter,
+                token_out_balance_before: result.tokenOutBalanceBefore,
+                token_out_balance_after: result.tokenOutBalanceAfter,
+            })
+        })
+    }
+
+    pub fn get_pool_data<DB: SimulationDatabase>(
+        &self,
+        session: &mut SimulationSession<DB>,
+    ) -> Result<SimulationOutcome<PoolData>> {
+        let calldata = Bytes::from(IPool::getPoolDataCall::new((self.pool,)).abi_encode());
+
+        session.call(self.simulator, calldata, |output| {
+            let (token_0, token_1, factory, fee) =
+                <(Address, Address, Address, u128)>::abi_decode(output, true)?;
+
+            Ok(PoolData {
+                token_0,
+                token_1,
+                factory,
+                fee,
+            })
+        })
+    }
+}
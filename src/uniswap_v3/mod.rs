@@ -0,0 +1,3 @@
+pub mod contracts;
+pub mod pool;
+pub mod swap_via_pool;
@@ -0,0 +1,53 @@
+use alloy_sol_types::SolValue;
+use revm::primitives::{keccak256, Address, U256};
+
+use crate::commons::errors::{Result, SimulationError};
+use crate::commons::registry::FactoryConfig;
+use crate::uniswap_v2::pair::sort_tokens;
+
+use super::contracts::PoolData;
+
+/// Computes offline the address the factory deploys the pool of `token_a` and `token_b` with the
+/// `fee` tier at, with `CREATE2` salted by the sorted tokens and the fee.
+///
+/// For more information check the contract code at: https://github.com/Uniswap/v3-periphery/blob/0682387198a24c7cd63566a2c58398533860a5d1/contracts/libraries/PoolAddress.sol#L33
+pub fn compute_pool_address(
+    factory: &FactoryConfig,
+    token_a: Address,
+    token_b: Address,
+    fee: u128,
+) -> Result<Address> {
+    let (token_0, token_1) = sort_tokens(token_a, token_b)?;
+    let salt = keccak256((token_0, token_1, U256::from(fee)).abi_encode());
+
+    Ok(factory.address.create2(salt, factory.init_code_hash))
+}
+
+/// Checks that `pool` is a genuine pool, like [`crate::uniswap_v2::pair::verify_pair`] does for
+/// the V2 pairs. Returns the factory of the pool.
+pub fn verify_pool<'a>(
+    pool: Address,
+    pool_data: &PoolData,
+    factories: &'a [FactoryConfig],
+) -> Result<&'a FactoryConfig> {
+    let factory = factories
+        .iter()
+        .find(|factory| factory.address == pool_data.factory)
+        .ok_or_else(|| {
+            SimulationError::Config(format!(
+                "The pool {pool} claims to be deployed by {}, which is not a known Uniswap V3 factory",
+                pool_data.factory
+            ))
+        })?;
+
+    let expected =
+        compute_pool_address(factory, pool_data.token_0, pool_data.token_1, pool_data.fee)?;
+    if expected != pool {
+        return Err(SimulationError::Config(format!(
+            "The pool {pool} is not the {} pool of {} and {} with a fee of {}, which lives at {expected}",
+            factory.name, pool_data.token_0, pool_data.token_1, pool_data.fee
+        )));
+    }
+
+    Ok(factory)
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use alloy_sol_types::{sol, SolCall};
+use common::{Assembler, MockErc20, SessionBuilder, RECIPIENT, SENDER, TOKEN};
+use revm::interpreter::opcode::*;
+use revm::primitives::{address, Address, Bytes, SpecId, B256, U256};
+use revm::InMemoryDB;
+use rust_revm_simulations::SimulationSession;
+
+sol! {
+    function transfer(address destination, uint value) public returns (bool);
+}
+
+const FORWARDER: Address = address!("00000000000000000000000000000000000000c1");
+const IDENTITY_PRECOMPILE: Address = address!("0000000000000000000000000000000000000004");
+
+// Forwards its calldata to the token after a call to the identity precompile, returning the
+// first word returned by the token.
+fn forwarder_bytecode() -> Bytes {
+    let mut asm = Assembler::default();
+    asm.op(CALLDATASIZE)
+        .push(U256::ZERO)
+        .push(U256::ZERO)
+        .op(CALLDATACOPY)
+        .push(U256::ZERO)
+        .push(U256::ZERO)
+        .push(U256::ZERO)
+        .push(U256::ZERO)
+        .push_bytes(IDENTITY_PRECOMPILE.as_slice())
+        .op(GAS)
+        .op(STATICCALL)
+        .op(POP)
+        .push(U256::from(32))
+        .push(U256::ZERO)
+        .op(CALLDATASIZE)
+        .push(U256::ZERO)
+        .push(U256::ZERO)
+        .push_bytes(TOKEN.as_slice())
+        .op(GAS)
+        .op(CALL)
+        .op(ISZERO)
+        .jumpi("revert")
+        .push(U256::from(32))
+        .push(U256::ZERO)
+        .op(RETURN)
+        .label("revert")
+        .push(U256::ZERO)
+        .op(DUP1)
+        .op(REVERT);
+
+    asm.assemble()
+}
+
+// Reads the balance of the coinbase.
+fn coinbase_reader_bytecode() -> Bytes {
+    let mut asm = Assembler::default();
+    asm.op(COINBASE).op(BALANCE).op(POP).op(STOP);
+
+    asm.assemble()
+}
+
+fn session_with_forwarder() -> (SimulationSession<InMemoryDB>, MockErc20) {
+    let token = MockErc20::default();
+    let session = SessionBuilder::default()
+        .token(TOKEN, token.clone())
+        .code(FORWARDER, forwarder_bytecode())
+        .balance(TOKEN, FORWARDER, U256::from(1_000))
+        .build();
+
+    (session, token)
+}
+
+fn transfer_calldata() -> Bytes {
+    Bytes::from(transferCall::new((RECIPIENT, U256::from(400))).abi_encode())
+}
+
+#[test]
+fn access_list_excludes_warm_by_default_addresses() {
+    let (mut session, token) = session_with_forwarder();
+
+    let (_, access_list) = session
+        .transact_with_access_list(FORWARDER, transfer_calldata())
+        .unwrap();
+
+    assert_eq!(access_list.len(), 1);
+    let item = &access_list[0];
+    assert_eq!(item.address, TOKEN);
+
+    let mut expected = vec![
+        B256::from(token.balance_slot(FORWARDER)),
+        B256::from(token.balance_slot(RECIPIENT)),
+    ];
+    expected.sort();
+    assert_eq!(item.storage_keys, expected);
+
+    for address in [SENDER, FORWARDER, IDENTITY_PRECOMPILE] {
+        assert!(access_list.iter().all(|item| item.address != address));
+    }
+}
+
+#[test]
+fn coinbase_is_only_warm_by_default_since_shanghai() {
+    let coinbase = common::block().header.miner;
+
+    for (spec_id, listed) in [(SpecId::SHANGHAI, false), (SpecId::LONDON, true)] {
+        let mut session = SessionBuilder::default()
+            .code(FORWARDER, coinbase_reader_bytecode())
+            .build()
+            .with_spec_id(spec_id);
+
+        let (_, access_list) = session
+            .transact_with_access_list(FORWARDER, Bytes::new())
+            .unwrap();
+
+        assert_eq!(
+            access_list.iter().any(|item| item.address == coinbase),
+            listed
+        );
+    }
+}
+
+#[test]
+fn access_list_is_attached_when_it_saves_gas() {
+    let (mut session, token) = session_with_forwarder();
+
+    let outcome = session
+        .execute(FORWARDER, transfer_calldata(), |_| Ok(()))
+        .unwrap();
+
+    let gas_with_access_list = outcome.gas_with_access_list.unwrap();
+    assert!(gas_with_access_list.gas_used < outcome.gas.gas_used);
+    assert_eq!(
+        outcome.access_list_gas_saved,
+        Some(outcome.gas.gas_used - gas_with_access_list.gas_used)
+    );
+    assert_eq!(
+        revm::Database::storage(session.database(), TOKEN, token.balance_slot(RECIPIENT)).unwrap(),
+        U256::from(400)
+    );
+}
+
+#[test]
+fn empty_access_list_is_not_attached() {
+    let mut session = SessionBuilder::default()
+        .token(TOKEN, MockErc20::default())
+        .balance(TOKEN, SENDER, U256::from(1_000))
+        .build();
+
+    let outcome = session
+        .execute(TOKEN, transfer_calldata(), |_| Ok(()))
+        .unwrap();
+
+    assert!(outcome.access_list.is_empty());
+    assert!(outcome.gas_with_access_list.is_none());
+    assert!(outcome.access_list_gas_saved.is_none());
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::rpc::MockRpc;
+use revm::primitives::{address, keccak256, Address, Bytes, Signature};
+use rust_revm_simulations::commons::{
+    submit_bundle, BuilderClient, Identity, SimulationError, FLASHBOTS_SIGNATURE_HEADER,
+};
+use serde_json::json;
+
+// First development account of anvil and hardhat.
+const AUTH_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const AUTH_ADDRESS: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+
+fn builder(url: &str) -> BuilderClient {
+    let auth_signer = Identity::from_private_key(AUTH_KEY)
+        .unwrap()
+        .into_signer()
+        .unwrap();
+
+    BuilderClient::new(url.parse().unwrap(), auth_signer)
+}
+
+#[tokio::test]
+async fn bundle_is_sent_for_each_target_block() {
+    let bundle_hash = "0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f";
+    let server = MockRpc::start(move |_| json!({"result": {"bundleHash": bundle_hash}})).await;
+    let transactions = vec![
+        Bytes::from_static(&[0x02, 0x01]),
+        Bytes::from_static(&[0x02, 0x02]),
+    ];
+
+    let submissions = submit_bundle(&[builder(&server.url)], &transactions, 100, 2)
+        .await
+        .unwrap();
+
+    assert_eq!(submissions.len(), 2);
+    assert!(submissions
+        .iter()
+        .all(|submission| submission.is_accepted()));
+    assert_eq!(submissions[0].bundle_hash.unwrap().to_string(), bundle_hash);
+    assert_eq!(submissions[1].block_number, 101);
+
+    let requests = server.requests();
+    for (request, block_number) in requests.iter().zip(["0x64", "0x65"]) {
+        let body = request.json();
+        assert_eq!(body["method"], "eth_sendBundle");
+        assert_eq!(body["params"][0]["txs"][0], "0x0201");
+        assert_eq!(body["params"][0]["txs"][1], "0x0202");
+        assert_eq!(body["params"][0]["blockNumber"], block_number);
+
+        // The header is `<address>:<signature of the hex encoded hash of the body>`.
+        let header = &request.headers[&FLASHBOTS_SIGNATURE_HEADER.to_lowercase()];
+        let (address, signature) = header.split_once(':').unwrap();
+        assert_eq!(address.parse::<Address>().unwrap(), AUTH_ADDRESS);
+
+        let signature = signature.parse::<Signature>().unwrap();
+        let body_hash = keccak256(request.body.as_bytes()).to_string();
+        assert_eq!(
+            signature.recover_address_from_msg(body_hash).unwrap(),
+            AUTH_ADDRESS
+        );
+    }
+}
+
+#[tokio::test]
+async fn refused_bundles_are_reported() {
+    let server =
+        MockRpc::start(|_| json!({"error": {"code": -32000, "message": "bundle already known"}}))
+            .await;
+
+    let error = submit_bundle(
+        &[builder(&server.url)],
+        &[Bytes::from_static(&[0x02])],
+        100,
+        1,
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(error, SimulationError::Rpc(_)));
+    assert!(error.to_string().contains("bundle already known"));
+}
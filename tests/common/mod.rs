@@ -0,0 +1,733 @@
+//! Test helpers shared by the integration tests.
+//!
+//! There is no Solidity toolchain available when running `cargo test`, so the contracts used by
+//! the offline tests are written directly in EVM assembly with the small assembler below.
+#![allow(dead_code)]
+
+pub mod rpc;
+
+use alloy_rpc_types::Block;
+use revm::interpreter::opcode::*;
+use revm::primitives::{address, keccak256, Address, Bytecode, Bytes, U256};
+use revm::InMemoryDB;
+use rust_revm_simulations::commons::{set_code, set_storage, SimulationDatabase};
+use rust_revm_simulations::SimulationSession;
+use std::collections::HashMap;
+
+pub const SENDER: Address = address!("FF3cF7b8582571095A2B05268A4E1BafBDAD060D");
+pub const RECIPIENT: Address = address!("00000000000000000000000000000000000000b0");
+pub const TOKEN: Address = address!("00000000000000000000000000000000000000a0");
+
+/// Block used by the in-memory sessions.
+pub fn block() -> Block {
+    let mut block: Block = Block::default();
+    block.header.number = 1;
+    block.header.timestamp = 1_700_000_000;
+    block.header.gas_limit = 30_000_000;
+    block.header.base_fee_per_gas = Some(7);
+    block
+}
+
+pub fn in_memory_session() -> SimulationSession<InMemoryDB> {
+    SimulationSession::in_memory(block(), SENDER)
+}
+
+/// Reserves of both tokens of the pairs deployed by the tests.
+pub fn reserve() -> U256 {
+    U256::from(10).pow(U256::from(24))
+}
+
+/// Amount swapped by the tests, small enough next to the reserves.
+pub fn amount() -> U256 {
+    U256::from(10).pow(U256::from(21))
+}
+
+/// Builds an in-memory session with the mock contracts deployed, e.g. tokens held by pairs.
+pub struct SessionBuilder {
+    session: SimulationSession<InMemoryDB>,
+    tokens: HashMap<Address, MockErc20>,
+}
+
+impl Default for SessionBuilder {
+    fn default() -> Self {
+        Self {
+            session: in_memory_session(),
+            tokens: HashMap::new(),
+        }
+    }
+}
+
+impl SessionBuilder {
+    pub fn token(mut self, token: Address, mock: MockErc20) -> Self {
+        mock.deploy(token, self.session.database());
+        self.tokens.insert(token, mock);
+        self
+    }
+
+    /// Writes the balance of `account` in a token deployed by the builder.
+    pub fn balance(mut self, token: Address, account: Address, amount: U256) -> Self {
+        let slot = self.tokens[&token].balance_slot(account);
+        set_storage(token, slot, amount, self.session.database()).unwrap();
+        self
+    }
+
+    /// Deploys the pair holding its reserves, its tokens being deployed by the builder.
+    pub fn pair(self, pair: Address, mock: MockPair, reserve_0: U256, reserve_1: U256) -> Self {
+        let mut builder =
+            self.balance(mock.token_0, pair, reserve_0)
+                .balance(mock.token_1, pair, reserve_1);
+        mock.deploy(pair, builder.session.database());
+        mock.set_reserves(pair, reserve_0, reserve_1, builder.session.database());
+        builder
+    }
+
+    pub fn code(mut self, account: Address, code: Bytes) -> Self {
+        set_code(account, Bytecode::new_raw(code), self.session.database()).unwrap();
+        self
+    }
+
+    pub fn build(self) -> SimulationSession<InMemoryDB> {
+        self.session
+    }
+}
+
+enum Item {
+    Op(u8),
+    Push(Vec<u8>),
+    Label(&'static str),
+    LabelRef(&'static str),
+}
+
+/// Minimal EVM assembler supporting labels, which are always referenced with `PUSH2`.
+#[derive(Default)]
+pub struct Assembler {
+    items: Vec<Item>,
+}
+
+impl Assembler {
+    pub fn op(&mut self, opcode: u8) -> &mut Self {
+        self.items.push(Item::Op(opcode));
+        self
+    }
+
+    pub fn push(&mut self, value: impl Into<U256>) -> &mut Self {
+        let value: U256 = value.into();
+        let bytes = value.to_be_bytes_trimmed_vec();
+        self.items
+            .push(Item::Push(if bytes.is_empty() { vec![0] } else { bytes }));
+        self
+    }
+
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.items.push(Item::Push(bytes.to_vec()));
+        self
+    }
+
+    pub fn label(&mut self, name: &'static str) -> &mut Self {
+        self.items.push(Item::Label(name));
+        self.items.push(Item::Op(JUMPDEST));
+        self
+    }
+
+    pub fn push_label(&mut self, name: &'static str) -> &mut Self {
+        self.items.push(Item::LabelRef(name));
+        self
+    }
+
+    pub fn jump(&mut self, name: &'static str) -> &mut Self {
+        self.push_label(name).op(JUMP)
+    }
+
+    pub fn jumpi(&mut self, name: &'static str) -> &mut Self {
+        self.push_label(name).op(JUMPI)
+    }
+
+    /// Stores the value on top of the stack at the given memory offset.
+    pub fn mstore_at(&mut self, offset: u64) -> &mut Self {
+        self.push(U256::from(offset)).op(MSTORE)
+    }
+
+    pub fn calldata_word(&mut self, index: u64) -> &mut Self {
+        self.push(U256::from(4 + 32 * index)).op(CALLDATALOAD)
+    }
+
+    /// Returns the value on top of the stack as a single ABI word.
+    pub fn return_word(&mut self) -> &mut Self {
+        self.mstore_at(0)
+            .push(U256::from(32))
+            .push(U256::ZERO)
+            .op(RETURN)
+    }
+
+    pub fn mload_at(&mut self, offset: u64) -> &mut Self {
+        self.push(U256::from(offset)).op(MLOAD)
+    }
+
+    pub fn assemble(&self) -> Bytes {
+        let mut labels = HashMap::new();
+        let mut offset = 0;
+        for item in &self.items {
+            match item {
+                Item::Op(_) => offset += 1,
+                Item::Push(bytes) => offset += 1 + bytes.len(),
+                Item::Label(name) => {
+                    labels.insert(*name, offset);
+                }
+                Item::LabelRef(_) => offset += 3,
+            }
+        }
+
+        let mut code = vec![];
+        for item in &self.items {
+            match item {
+                Item::Op(opcode) => code.push(*opcode),
+                Item::Push(bytes) => {
+                    code.push(PUSH0 + bytes.len() as u8);
+                    code.extend_from_slice(bytes);
+                }
+                Item::Label(_) => {}
+                Item::LabelRef(name) => {
+                    code.push(PUSH2);
+                    code.extend_from_slice(&(labels[name] as u16).to_be_bytes());
+                }
+            }
+        }
+
+        Bytes::from(code)
+    }
+}
+
+/// Storage layout of the mappings of [`MockErc20`].
+#[derive(Debug, Clone, Copy)]
+pub enum MappingLayout {
+    /// `keccak256(key . slot)`
+    Solidity,
+    /// `keccak256(slot . key)`
+    Vyper,
+}
+
+/// What [`MockErc20`] returns from `transfer`, `transferFrom` and `approve`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockReturn {
+    True,
+    /// Nothing, like USDT.
+    Nothing,
+    /// `false`, without changing anything.
+    False,
+}
+
+/// Hand written ERC20 used by the offline tests.
+#[derive(Debug, Clone)]
+pub struct MockErc20 {
+    pub layout: MappingLayout,
+    pub balances_slot: U256,
+    pub allowances_slot: U256,
+    pub decimals: u8,
+    /// Returned as an ABI string, hence at most 32 bytes. Not implemented when empty.
+    pub symbol: &'static str,
+    /// Share of every transfer burnt by the token, in basis points.
+    pub transfer_tax_bps: u64,
+    /// Account the token refuses to transfer to, such as the pool for tokens that cannot be sold.
+    pub blocked_recipient: Option<Address>,
+    /// Extra getters returning a constant whatever their arguments, by signature.
+    pub getters: Vec<(&'static str, U256)>,
+    pub returns: MockReturn,
+    /// Whether `approve` reverts when changing an allowance that is not zero, like USDT.
+    pub approve_requires_zero: bool,
+}
+
+impl Default for MockErc20 {
+    fn default() -> Self {
+        Self {
+            layout: MappingLayout::Solidity,
+            balances_slot: U256::from(3),
+            allowances_slot: U256::from(4),
+            decimals: 18,
+            symbol: "MOCK",
+            transfer_tax_bps: 0,
+            blocked_recipient: None,
+            getters: vec![],
+            returns: MockReturn::True,
+            approve_requires_zero: false,
+        }
+    }
+}
+
+impl MockErc20 {
+    pub fn deploy<DB: SimulationDatabase>(&self, token: Address, database: &mut DB) {
+        set_code(token, Bytecode::new_raw(self.bytecode()), database).unwrap();
+    }
+
+    pub fn balance_slot(&self, account: Address) -> U256 {
+        let key = account.into_word();
+        let slot = self.balances_slot.to_be_bytes::<32>();
+        let preimage = match self.layout {
+            MappingLayout::Solidity => [key.as_slice(), &slot].concat(),
+            MappingLayout::Vyper => [slot.as_slice(), key.as_slice()].concat(),
+        };
+
+        keccak256(preimage).into()
+    }
+
+    pub fn bytecode(&self) -> Bytes {
+        let mut asm = Assembler::default();
+
+        asm.push(U256::ZERO)
+            .op(CALLDATALOAD)
+            .push(U256::from(224))
+            .op(SHR);
+        for (selector, label) in [
+            (0x70a08231u32, "balance_of"),
+            (0xa9059cbb, "transfer"),
+            (0x095ea7b3, "approve"),
+            (0xdd62ed3e, "allowance"),
+            (0x23b872dd, "transfer_from"),
+            (0x313ce567, "decimals"),
+        ] {
+            asm.op(DUP1).push(U256::from(selector)).op(EQ).jumpi(label);
+        }
+        if !self.symbol.is_empty() {
+            asm.op(DUP1)
+                .push(U256::from(0x95d89b41u32))
+                .op(EQ)
+                .jumpi("symbol");
+        }
+        let getters = self
+            .getters
+            .iter()
+            .map(|(signature, value)| {
+                let label: &'static str = Box::leak(format!("getter {signature}").into_boxed_str());
+                (keccak256(signature)[..4].to_vec(), label, *value)
+            })
+            .collect::<Vec<_>>();
+        for (selector, label, _) in &getters {
+            asm.op(DUP1).push_bytes(selector).op(EQ).jumpi(label);
+        }
+        asm.label("revert").push(U256::ZERO).op(DUP1).op(REVERT);
+
+        for (_, label, value) in getters {
+            asm.label(label).push(value).return_word();
+        }
+
+        asm.label("balance_of").calldata_word(0);
+        self.mapping_slot(&mut asm, self.balances_slot);
+        asm.op(SLOAD).return_word();
+
+        // transfer(to, amount)
+        asm.label("transfer");
+        self.return_false(&mut asm);
+        asm.op(CALLER).calldata_word(0).calldata_word(1);
+        self.move_balance(&mut asm);
+        self.return_success(&mut asm);
+
+        // approve(spender, amount)
+        asm.label("approve");
+        self.return_false(&mut asm);
+        asm.calldata_word(1).calldata_word(0).op(CALLER);
+        self.allowance_slot(&mut asm);
+        if self.approve_requires_zero {
+            // require(amount == 0 || allowance == 0)
+            asm.op(DUP1)
+                .op(SLOAD)
+                .op(ISZERO)
+                .op(ISZERO)
+                .op(DUP3)
+                .op(ISZERO)
+                .op(ISZERO)
+                .op(AND)
+                .jumpi("revert");
+        }
+        asm.op(SSTORE);
+        self.return_success(&mut asm);
+
+        // allowance(owner, spender)
+        asm.label("allowance").calldata_word(1).calldata_word(0);
+        self.allowance_slot(&mut asm);
+        asm.op(SLOAD).return_word();
+
+        // transferFrom(from, to, amount)
+        asm.label("transfer_from");
+        self.return_false(&mut asm);
+        asm.calldata_word(2).op(CALLER).calldata_word(0);
+        self.allowance_slot(&mut asm);
+        asm.op(DUP1)
+            .op(SLOAD)
+            .op(DUP1)
+            .op(DUP4)
+            .op(GT)
+            .jumpi("revert")
+            .op(DUP3)
+            .op(SWAP1)
+            .op(SUB)
+            .op(SWAP1)
+            .op(SSTORE)
+            .op(POP)
+            .calldata_word(0)
+            .calldata_word(1)
+            .calldata_word(2);
+        self.move_balance(&mut asm);
+        self.return_success(&mut asm);
+
+        asm.label("decimals")
+            .push(U256::from(self.decimals))
+            .return_word();
+
+        if !self.symbol.is_empty() {
+            let mut symbol = [0u8; 32];
+            symbol[..self.symbol.len()].copy_from_slice(self.symbol.as_bytes());
+            asm.label("symbol")
+                .push(U256::from(32))
+                .mstore_at(0)
+                .push(U256::from(self.symbol.len()))
+                .mstore_at(32)
+                .push_bytes(&symbol)
+                .mstore_at(64)
+                .push(U256::from(96))
+                .push(U256::ZERO)
+                .op(RETURN);
+        }
+
+        asm.assemble()
+    }
+
+    fn return_success(&self, asm: &mut Assembler) {
+        match self.returns {
+            MockReturn::True => asm.push(U256::from(1)).return_word(),
+            MockReturn::Nothing | MockReturn::False => asm.op(STOP),
+        };
+    }
+
+    // Returns `false` straight away for the tokens doing so.
+    fn return_false(&self, asm: &mut Assembler) {
+        if self.returns == MockReturn::False {
+            asm.push(U256::ZERO).return_word();
+        }
+    }
+
+    // [key] -> [slot]
+    fn mapping_slot(&self, asm: &mut Assembler, slot: U256) {
+        match self.layout {
+            MappingLayout::Solidity => asm.mstore_at(0).push(slot).mstore_at(32),
+            MappingLayout::Vyper => asm.mstore_at(32).push(slot).mstore_at(0),
+        };
+        asm.push(U256::from(64)).push(U256::ZERO).op(KECCAK256);
+    }
+
+    // [spender, owner] -> [slot]
+    fn allowance_slot(&self, asm: &mut Assembler) {
+        self.mapping_slot(asm, self.allowances_slot);
+        match self.layout {
+            MappingLayout::Solidity => asm.op(SWAP1).mstore_at(0).mstore_at(32),
+            MappingLayout::Vyper => asm.mstore_at(0).mstore_at(32),
+        };
+        asm.push(U256::from(64)).push(U256::ZERO).op(KECCAK256);
+    }
+
+    // [from, to, amount] -> []
+    fn move_balance(&self, asm: &mut Assembler) {
+        if let Some(blocked_recipient) = self.blocked_recipient {
+            asm.op(DUP2)
+                .push_bytes(blocked_recipient.as_slice())
+                .op(EQ)
+                .jumpi("revert");
+        }
+        asm.op(DUP3);
+        self.mapping_slot(asm, self.balances_slot);
+        asm.op(DUP1)
+            .op(SLOAD)
+            .op(DUP1)
+            .op(DUP4)
+            .op(GT)
+            .jumpi("revert")
+            .op(DUP3)
+            .op(SWAP1)
+            .op(SUB)
+            .op(SWAP1)
+            .op(SSTORE);
+        if self.transfer_tax_bps > 0 {
+            // amount - amount * bps / 10000
+            asm.op(DUP1)
+                .push(U256::from(self.transfer_tax_bps))
+                .op(MUL)
+                .push(U256::from(10_000))
+                .op(SWAP1)
+                .op(DIV)
+                .op(SWAP1)
+                .op(SUB);
+        }
+        asm.op(DUP2);
+        self.mapping_slot(asm, self.balances_slot);
+        asm.op(DUP1).op(SLOAD).op(DUP3).op(ADD).op(SWAP1).op(SSTORE);
+
+        // emit Transfer(from, to, amount)
+        asm.mstore_at(0)
+            .op(SWAP1)
+            .push_bytes(keccak256("Transfer(address,address,uint256)").as_slice())
+            .push(U256::from(32))
+            .push(U256::ZERO)
+            .op(LOG3);
+    }
+}
+
+/// Hand written Uniswap V2 pair, keeping its reserves in two plain slots.
+///
+/// The swap follows the original pair: the outputs are sent first, the inputs are taken from the
+/// balances minus the reserves, and the fee adjusted balances must keep the constant product.
+#[derive(Debug, Clone)]
+pub struct MockPair {
+    pub token_0: Address,
+    pub token_1: Address,
+    /// The fee is `fee / fee_denominator` of the input, 3/1000 for Uniswap.
+    pub fee: u64,
+    pub fee_denominator: u64,
+    /// Returned by `factory`, which is not implemented without one.
+    pub factory: Option<Address>,
+}
+
+const RESERVE_0_SLOT: u64 = 8;
+const RESERVE_1_SLOT: u64 = 9;
+
+// Memory used by the swap to keep its variables.
+const BALANCE_0: u64 = 0x100;
+const BALANCE_1: u64 = 0x120;
+const AMOUNT_0_IN: u64 = 0x140;
+const AMOUNT_1_IN: u64 = 0x160;
+const AMOUNT_0_OUT: u64 = 0x180;
+const AMOUNT_1_OUT: u64 = 0x1a0;
+
+impl MockPair {
+    pub fn new(token_0: Address, token_1: Address) -> Self {
+        Self {
+            token_0,
+            token_1,
+            fee: 3,
+            fee_denominator: 1000,
+            factory: None,
+        }
+    }
+
+    pub fn deploy<DB: SimulationDatabase>(&self, pair: Address, database: &mut DB) {
+        set_code(pair, Bytecode::new_raw(self.bytecode()), database).unwrap();
+    }
+
+    /// Sets the reserves, which the pair balances are expected to match.
+    pub fn set_reserves<DB: SimulationDatabase>(
+        &self,
+        pair: Address,
+        reserve_0: U256,
+        reserve_1: U256,
+        database: &mut DB,
+    ) {
+        set_storage(pair, U256::from(RESERVE_0_SLOT), reserve_0, database).unwrap();
+        set_storage(pair, U256::from(RESERVE_1_SLOT), reserve_1, database).unwrap();
+    }
+
+    pub fn bytecode(&self) -> Bytes {
+        let mut asm = Assembler::default();
+
+        asm.push(U256::ZERO)
+            .op(CALLDATALOAD)
+            .push(U256::from(224))
+            .op(SHR);
+        for (selector, label) in [
+            (0x0dfe1681u32, "token0"),
+            (0xd21220a7, "token1"),
+            (0x0902f1ac, "get_reserves"),
+            (0x022c0d9f, "swap"),
+        ] {
+            asm.op(DUP1).push(U256::from(selector)).op(EQ).jumpi(label);
+        }
+        if self.factory.is_some() {
+            asm.op(DUP1)
+                .push(U256::from(0xc45a0155u32))
+                .op(EQ)
+                .jumpi("factory");
+        }
+        asm.label("revert").push(U256::ZERO).op(DUP1).op(REVERT);
+
+        if let Some(factory) = self.factory {
+            asm.label("factory")
+                .push_bytes(factory.as_slice())
+                .return_word();
+        }
+
+        asm.label("token0")
+            .push_bytes(self.token_0.as_slice())
+            .return_word();
+        asm.label("token1")
+            .push_bytes(self.token_1.as_slice())
+            .return_word();
+
+        asm.label("get_reserves")
+            .push(U256::from(RESERVE_0_SLOT))
+            .op(SLOAD)
+            .mstore_at(0)
+            .push(U256::from(RESERVE_1_SLOT))
+            .op(SLOAD)
+            .mstore_at(32)
+            .push(U256::ZERO)
+            .mstore_at(64)
+            .push(U256::from(96))
+            .push(U256::ZERO)
+            .op(RETURN);
+
+        // swap(amount0Out, amount1Out, to, data)
+        asm.label("swap");
+        asm.calldata_word(0).mstore_at(AMOUNT_0_OUT);
+        asm.calldata_word(1).mstore_at(AMOUNT_1_OUT);
+        for (token, amount_out, skip) in [
+            (self.token_0, AMOUNT_0_OUT, "skip_transfer_0"),
+            (self.token_1, AMOUNT_1_OUT, "skip_transfer_1"),
+        ] {
+            asm.mload_at(amount_out).op(ISZERO).jumpi(skip);
+            Self::transfer(&mut asm, token, amount_out);
+            asm.label(skip);
+        }
+        Self::balance_of_self(&mut asm, self.token_0);
+        asm.mstore_at(BALANCE_0);
+        Self::balance_of_self(&mut asm, self.token_1);
+        asm.mstore_at(BALANCE_1);
+
+        for (reserve_slot, balance, amount_out, amount_in, (no_input, done)) in [
+            (
+                RESERVE_0_SLOT,
+                BALANCE_0,
+                AMOUNT_0_OUT,
+                AMOUNT_0_IN,
+                ("no_input_0", "input_0_done"),
+            ),
+            (
+                RESERVE_1_SLOT,
+                BALANCE_1,
+                AMOUNT_1_OUT,
+                AMOUNT_1_IN,
+                ("no_input_1", "input_1_done"),
+            ),
+        ] {
+            // require(amountOut < reserve), then the input is what exceeds reserve - amountOut.
+            asm.mload_at(amount_out)
+                .push(U256::from(reserve_slot))
+                .op(SLOAD)
+                .op(DUP2)
+                .op(DUP2)
+                .op(GT)
+                .op(ISZERO)
+                .jumpi("revert")
+                .op(SUB)
+                .mload_at(balance)
+                .op(DUP2)
+                .op(DUP2)
+                .op(GT)
+                .op(ISZERO)
+                .jumpi(no_input)
+                .op(SUB)
+                .jump(done)
+                .label(no_input)
+                .op(POP)
+                .op(POP)
+                .push(U256::ZERO)
+                .label(done)
+                .mstore_at(amount_in);
+        }
+        asm.mload_at(AMOUNT_0_IN)
+            .mload_at(AMOUNT_1_IN)
+            .op(OR)
+            .op(ISZERO)
+            .jumpi("revert");
+
+        // reserve0 * reserve1 * denominator^2 <= adjusted0 * adjusted1
+        let denominator = U256::from(self.fee_denominator);
+        asm.push(U256::from(RESERVE_0_SLOT))
+            .op(SLOAD)
+            .push(U256::from(RESERVE_1_SLOT))
+            .op(SLOAD)
+            .op(MUL)
+            .push(denominator * denominator)
+            .op(MUL);
+        for (balance, amount_in) in [(BALANCE_0, AMOUNT_0_IN), (BALANCE_1, AMOUNT_1_IN)] {
+            asm.mload_at(balance)
+                .push(denominator)
+                .op(MUL)
+                .mload_at(amount_in)
+                .push(U256::from(self.fee))
+                .op(MUL)
+                .op(SWAP1)
+                .op(SUB);
+        }
+        asm.op(MUL).op(LT).jumpi("revert");
+
+        asm.mload_at(BALANCE_0)
+            .push(U256::from(RESERVE_0_SLOT))
+            .op(SSTORE)
+            .mload_at(BALANCE_1)
+            .push(U256::from(RESERVE_1_SLOT))
+            .op(SSTORE);
+
+        // emit Swap(sender, amount0In, amount1In, amount0Out, amount1Out, to)
+        asm.calldata_word(2)
+            .op(CALLER)
+            .push_bytes(
+                keccak256("Swap(address,uint256,uint256,uint256,uint256,address)").as_slice(),
+            )
+            .push(U256::from(128))
+            .push(U256::from(AMOUNT_0_IN))
+            .op(LOG3)
+            .op(STOP);
+
+        asm.assemble()
+    }
+
+    // Calls `token.transfer(to, amount)`, with `to` the third argument of the swap and the amount
+    // read from memory.
+    fn transfer(asm: &mut Assembler, token: Address, amount: u64) {
+        asm.push(U256::from(0xa9059cbbu64) << 224)
+            .mstore_at(0)
+            .calldata_word(2)
+            .mstore_at(4)
+            .mload_at(amount)
+            .mstore_at(36)
+            .push(U256::from(32))
+            .push(U256::ZERO)
+            .push(U256::from(68))
+            .push(U256::ZERO)
+            .push(U256::ZERO)
+            .push_bytes(token.as_slice())
+            .op(GAS)
+            .op(CALL)
+            .op(ISZERO)
+            .jumpi("revert");
+    }
+
+    // Pushes `token.balanceOf(address(this))`.
+    fn balance_of_self(asm: &mut Assembler, token: Address) {
+        asm.push(U256::from(0x70a08231u64) << 224)
+            .mstore_at(0)
+            .op(ADDRESS)
+            .mstore_at(4)
+            .push(U256::from(32))
+            .push(U256::ZERO)
+            .push(U256::from(36))
+            .push(U256::ZERO)
+            .push_bytes(token.as_slice())
+            .op(GAS)
+            .op(STATICCALL)
+            .op(ISZERO)
+            .jumpi("revert")
+            .mload_at(0);
+    }
+}
+
+/// Factory whose `getPair` returns the same pair whatever the tokens.
+#[derive(Debug, Clone)]
+pub struct MockFactory {
+    pub pair: Address,
+}
+
+impl MockFactory {
+    pub fn deploy<DB: SimulationDatabase>(&self, factory: Address, database: &mut DB) {
+        let mut asm = Assembler::default();
+        asm.push_bytes(self.pair.as_slice()).return_word();
+
+        set_code(factory, Bytecode::new_raw(asm.assemble()), database).unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+mod common;
+
+use common::{MockErc20, MockReturn, SessionBuilder, RECIPIENT, SENDER, TOKEN};
+use revm::primitives::U256;
+use rust_revm_simulations::commons::{ExecutionStatus, RevertReason, SimulationError};
+use rust_revm_simulations::{Erc20, SimulationSession};
+
+fn session_with_token() -> (SimulationSession<revm::InMemoryDB>, MockErc20) {
+    let token = MockErc20::default();
+
+    (session_with(token.clone()), token)
+}
+
+fn session_with(mock: MockErc20) -> SimulationSession<revm::InMemoryDB> {
+    SessionBuilder::default().token(TOKEN, mock).build()
+}
+
+#[test]
+fn set_balance_writes_the_balance_slot() {
+    let (mut session, mock) = session_with_token();
+    let token = Erc20::new(TOKEN);
+
+    token
+        .set_balance(SENDER, U256::from(1_000), &mut session)
+        .unwrap();
+
+    let balance = token.balance_of(SENDER, &mut session).unwrap().decoded;
+    assert_eq!(balance, U256::from(1_000));
+    assert_eq!(
+        revm::Database::storage(session.database(), TOKEN, mock.balance_slot(SENDER)).unwrap(),
+        U256::from(1_000)
+    );
+}
+
+#[test]
+fn transfer_moves_balances_in_memory() {
+    let (mut session, _) = session_with_token();
+    let token = Erc20::new(TOKEN);
+    token
+        .set_balance(SENDER, U256::from(1_000), &mut session)
+        .unwrap();
+
+    let transfer = token
+        .transfer(RECIPIENT, U256::from(400), &mut session)
+        .unwrap();
+
+    assert!(transfer.decoded);
+    assert_eq!(transfer.status, ExecutionStatus::Success);
+    assert_eq!(transfer.logs.len(), 1);
+
+    let token_diff = transfer
+        .state_diff
+        .iter()
+        .find(|account| account.address == TOKEN)
+        .unwrap();
+    assert_eq!(token_diff.storage.len(), 2);
+    assert_eq!(
+        token.balance_of(SENDER, &mut session).unwrap().decoded,
+        U256::from(600)
+    );
+    assert_eq!(
+        token.balance_of(RECIPIENT, &mut session).unwrap().decoded,
+        U256::from(400)
+    );
+}
+
+#[test]
+fn approve_sets_the_allowance() {
+    let (mut session, _) = session_with_token();
+    let token = Erc20::new(TOKEN);
+
+    let approve = token
+        .approve(RECIPIENT, U256::from(25), &mut session)
+        .unwrap();
+
+    assert!(approve.decoded);
+    assert_eq!(
+        token
+            .allowance(SENDER, RECIPIENT, &mut session)
+            .unwrap()
+            .decoded,
+        U256::from(25)
+    );
+}
+
+#[test]
+fn transfer_without_balance_reverts() {
+    let (mut session, _) = session_with_token();
+    let token = Erc20::new(TOKEN);
+
+    let error = token
+        .transfer(RECIPIENT, U256::from(1), &mut session)
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        SimulationError::Revert {
+            reason: RevertReason::Empty,
+            ..
+        }
+    ));
+    assert_eq!(error.exit_code(), 4);
+}
+
+#[test]
+fn metadata_is_read_and_cached() {
+    let mut session = session_with(MockErc20 {
+        decimals: 6,
+        symbol: "USDC",
+        ..MockErc20::default()
+    });
+    let token = Erc20::new(TOKEN);
+
+    let metadata = token.metadata(&mut session).unwrap();
+    assert_eq!(metadata.symbol, "USDC");
+    assert_eq!(metadata.decimals, 6);
+    // The token does not implement `name`.
+    assert_eq!(metadata.name, "");
+    assert_eq!(
+        metadata.format_amount(U256::from(1_500_000)),
+        "1500000 (1.5 USDC)"
+    );
+
+    // Replacing the code does not change the cached metadata.
+    MockErc20::default().deploy(TOKEN, session.database());
+    assert_eq!(token.metadata(&mut session).unwrap(), metadata);
+}
+
+#[test]
+fn bytes32_symbols_are_supported() {
+    let mut symbol = [0u8; 32];
+    symbol[..3].copy_from_slice(b"MKR");
+    let mut session = session_with(MockErc20 {
+        symbol: "",
+        getters: vec![("symbol()", U256::from_be_bytes(symbol))],
+        ..MockErc20::default()
+    });
+
+    let metadata = Erc20::new(TOKEN).metadata(&mut session).unwrap();
+    assert_eq!(metadata.symbol, "MKR");
+}
+
+#[test]
+fn amounts_with_a_decimal_point_are_scaled_by_the_decimals() {
+    let mut session = session_with(MockErc20 {
+        decimals: 6,
+        ..MockErc20::default()
+    });
+    let token = Erc20::new(TOKEN);
+
+    assert_eq!(
+        token.parse_amount("1.5", &mut session).unwrap(),
+        U256::from(1_500_000)
+    );
+    assert_eq!(
+        token.parse_amount("1500", &mut session).unwrap(),
+        U256::from(1_500)
+    );
+}
+
+#[test]
+fn transfers_returning_nothing_succeed() {
+    let mut session = session_with(MockErc20 {
+        returns: MockReturn::Nothing,
+        ..MockErc20::default()
+    });
+    let token = Erc20::new(TOKEN);
+    token
+        .set_balance(SENDER, U256::from(1_000), &mut session)
+        .unwrap();
+
+    let transfer = token
+        .transfer(RECIPIENT, U256::from(400), &mut session)
+        .unwrap();
+
+    assert!(transfer.decoded);
+    assert_eq!(
+        token.balance_of(RECIPIENT, &mut session).unwrap().decoded,
+        U256::from(400)
+    );
+}
+
+#[test]
+fn transfers_returning_false_fail() {
+    let mut session = session_with(MockErc20 {
+        returns: MockReturn::False,
+        ..MockErc20::default()
+    });
+    let token = Erc20::new(TOKEN);
+    token
+        .set_balance(SENDER, U256::from(1_000), &mut session)
+        .unwrap();
+
+    let error = token
+        .transfer(RECIPIENT, U256::from(400), &mut session)
+        .unwrap_err();
+
+    assert!(matches!(error, SimulationError::InvariantViolation(_)));
+    assert_eq!(error.exit_code(), 7);
+}
+
+#[test]
+fn force_approve_resets_a_non_zero_allowance() {
+    let mut session = session_with(MockErc20 {
+        returns: MockReturn::Nothing,
+        approve_requires_zero: true,
+        ..MockErc20::default()
+    });
+    let token = Erc20::new(TOKEN);
+
+    token
+        .approve(RECIPIENT, U256::from(25), &mut session)
+        .unwrap();
+    assert!(token
+        .approve(RECIPIENT, U256::from(50), &mut session)
+        .is_err());
+
+    let approvals = token
+        .force_approve(RECIPIENT, U256::from(50), &mut session)
+        .unwrap();
+
+    assert_eq!(approvals.len(), 2);
+    assert_eq!(
+        token
+            .allowance(SENDER, RECIPIENT, &mut session)
+            .unwrap()
+            .decoded,
+        U256::from(50)
+    );
+}
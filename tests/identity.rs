@@ -0,0 +1,50 @@
+use revm::primitives::{address, hex};
+use rust_revm_simulations::commons::{Identity, SimulationError};
+
+// First development account of anvil and hardhat.
+const PRIVATE_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+#[test]
+fn private_key_derives_its_address() {
+    let identity = Identity::from_private_key(PRIVATE_KEY).unwrap();
+
+    assert_eq!(
+        identity.address(),
+        address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266")
+    );
+    assert!(identity.signer().is_some());
+}
+
+#[test]
+fn invalid_private_key_is_a_config_error() {
+    let error = Identity::from_private_key("0x1234").unwrap_err();
+
+    assert!(matches!(error, SimulationError::Config(_)));
+}
+
+#[test]
+fn keystore_is_decrypted_with_its_password() {
+    let directory = std::env::temp_dir().join(format!("keystore-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let private_key = hex::decode(PRIVATE_KEY).unwrap();
+    alloy_signer_local::PrivateKeySigner::encrypt_keystore(
+        &directory,
+        &mut rand::thread_rng(),
+        private_key,
+        "password",
+        Some("hot-wallet.json"),
+    )
+    .unwrap();
+    let keystore = directory.join("hot-wallet.json");
+
+    let identity = Identity::from_keystore(&keystore, "password").unwrap();
+    let wrong_password = Identity::from_keystore(&keystore, "wrong password");
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(
+        identity.address(),
+        address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266")
+    );
+    assert!(matches!(wrong_password, Err(SimulationError::Config(_))));
+}
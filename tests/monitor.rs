@@ -0,0 +1,139 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use alloy_provider::ProviderBuilder;
+use alloy_rpc_types::Header;
+use common::rpc::MockRpc;
+use common::{RECIPIENT, SENDER, TOKEN};
+use revm::primitives::{address, b256, keccak256, AccessList, Address, Bytes, B256, U256};
+use rust_revm_simulations::commons::{monitor_inclusion, SignedTransaction, Subscription};
+use rust_revm_simulations::SwapResult;
+use serde_json::{json, Value};
+
+const POOL: Address = address!("00000000000000000000000000000000000000c0");
+const SWAP_HASH: B256 = b256!("1111111111111111111111111111111111111111111111111111111111111111");
+
+fn new_heads(blocks: usize) -> Subscription<Header> {
+    Box::pin(futures::stream::iter(
+        (0..blocks).map(|_| Ok(Header::default())),
+    ))
+}
+
+fn swap() -> SwapResult {
+    SwapResult {
+        pool: POOL,
+        token_in: RECIPIENT,
+        token_out: TOKEN,
+        amount_in: U256::from(1_000),
+        amount_out: U256::from(500),
+        steps: vec![],
+        token_in_metadata: None,
+        token_out_metadata: None,
+        input_tax: None,
+        output_tax: None,
+        route: vec![],
+        gas_saved: None,
+        transactions: vec![SignedTransaction {
+            name: "Pool Swap".to_owned(),
+            hash: SWAP_HASH,
+            from: SENDER,
+            to: POOL,
+            chain_id: 1,
+            nonce: 0,
+            gas_limit: 120_000,
+            simulated_gas_used: 100_000,
+            max_fee_per_gas: 20,
+            max_priority_fee_per_gas: 1,
+            input: Bytes::new(),
+            access_list: AccessList::default(),
+            raw: Bytes::new(),
+        }],
+        bundles: vec![],
+        inclusion: None,
+    }
+}
+
+/// Receipt of the swap, transferring 490 tokens out of the pool to the sender.
+fn receipt() -> Value {
+    let block_hash = B256::repeat_byte(0x22);
+    let transfer = json!({
+        "address": TOKEN,
+        "topics": [
+            keccak256("Transfer(address,address,uint256)"),
+            POOL.into_word(),
+            SENDER.into_word(),
+        ],
+        "data": B256::from(U256::from(490)),
+        "blockHash": block_hash,
+        "blockNumber": "0x2a",
+        "transactionHash": SWAP_HASH,
+        "transactionIndex": "0x0",
+        "logIndex": "0x0",
+        "removed": false,
+    });
+
+    json!({
+        "type": "0x2",
+        "status": "0x1",
+        "cumulativeGasUsed": "0x186a0",
+        "logs": [transfer],
+        "logsBloom": Bytes::from(vec![0; 256]),
+        "transactionHash": SWAP_HASH,
+        "transactionIndex": "0x0",
+        "blockHash": block_hash,
+        "blockNumber": "0x2a",
+        "gasUsed": "0x17318",
+        "effectiveGasPrice": "0xa",
+        "from": SENDER,
+        "to": POOL,
+        "contractAddress": null,
+    })
+}
+
+#[tokio::test]
+async fn inclusion_is_compared_with_the_simulation() {
+    // The transaction is only found at the second block.
+    let calls = AtomicUsize::new(0);
+    let node = MockRpc::start(move |request| {
+        assert_eq!(request["method"], "eth_getTransactionReceipt");
+        match calls.fetch_add(1, Ordering::SeqCst) {
+            0 => json!({"result": null}),
+            _ => json!({"result": receipt()}),
+        }
+    })
+    .await;
+    let client = ProviderBuilder::new().on_http(node.url.parse().unwrap());
+
+    let report = monitor_inclusion(&client, new_heads(5), &swap(), 5)
+        .await
+        .unwrap();
+
+    assert_eq!(report.blocks_watched, 2);
+    assert!(report.missing.is_empty());
+    assert_eq!(report.included.len(), 1);
+    let included = &report.included[0];
+    assert_eq!(included.block_number, 42);
+    assert!(included.success);
+    assert_eq!(included.effective_gas_price, 10);
+    assert_eq!(included.gas_used, 95_000);
+    assert_eq!(included.simulated_gas_used, 100_000);
+    assert_eq!(report.amount_out, Some(U256::from(490)));
+    assert_eq!(report.simulated_amount_out, U256::from(500));
+}
+
+#[tokio::test]
+async fn monitor_gives_up_after_max_blocks() {
+    let node = MockRpc::start(|_| json!({"result": null})).await;
+    let client = ProviderBuilder::new().on_http(node.url.parse().unwrap());
+
+    let report = monitor_inclusion(&client, new_heads(10), &swap(), 3)
+        .await
+        .unwrap();
+
+    assert_eq!(report.blocks_watched, 3);
+    assert_eq!(node.requests().len(), 3);
+    assert!(report.included.is_empty());
+    assert_eq!(report.missing, vec![SWAP_HASH]);
+    assert_eq!(report.amount_out, None);
+}
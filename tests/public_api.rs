@@ -0,0 +1,49 @@
+use std::any::type_name;
+
+use rust_revm_simulations::commons::{
+    AccessListInspector, AccountDiff, AlloyCacheDB, BuilderClient, BundleSubmission, ChainConfig,
+    EthWsSubscriptionRequest, ExecutionStatus, FactoryConfig, GasReport, HashOrder, Identity,
+    IncludedTransaction, InclusionReport, Registry, RevertReason, SignedTransaction,
+    SimulationError, SimulationOutcome, SimulationStep, StorageChange, StorageMapping,
+    TokenMetadata, TransactionSettings, TransferTax,
+};
+use rust_revm_simulations::{Erc20, ExecutionStrategy, SimulationSession, SwapResult};
+
+#[test]
+fn types_of_the_public_api_can_be_named() {
+    let names = [
+        type_name::<AccessListInspector>(),
+        type_name::<AccountDiff>(),
+        type_name::<BuilderClient>(),
+        type_name::<BundleSubmission>(),
+        type_name::<ChainConfig>(),
+        type_name::<EthWsSubscriptionRequest>(),
+        type_name::<ExecutionStatus>(),
+        type_name::<FactoryConfig>(),
+        type_name::<GasReport>(),
+        type_name::<HashOrder>(),
+        type_name::<Identity>(),
+        type_name::<IncludedTransaction>(),
+        type_name::<InclusionReport>(),
+        type_name::<Registry>(),
+        type_name::<RevertReason>(),
+        type_name::<SignedTransaction>(),
+        type_name::<SimulationError>(),
+        type_name::<SimulationOutcome<()>>(),
+        type_name::<SimulationStep>(),
+        type_name::<StorageChange>(),
+        type_name::<StorageMapping>(),
+        type_name::<TokenMetadata>(),
+        type_name::<TransactionSettings>(),
+        type_name::<TransferTax>(),
+        type_name::<Erc20>(),
+        type_name::<ExecutionStrategy>(),
+        type_name::<SwapResult>(),
+    ];
+    assert!(names
+        .iter()
+        .all(|name| name.starts_with("rust_revm_simulations::")));
+
+    // Forked sessions are backed by the fork database.
+    assert!(type_name::<SimulationSession<AlloyCacheDB<'static>>>().contains("CacheDB"));
+}
@@ -0,0 +1,77 @@
+use revm::primitives::{address, b256};
+use rust_revm_simulations::commons::{Registry, SimulationError};
+
+#[test]
+fn defaults_include_mainnet() {
+    let registry = Registry::default();
+
+    let mainnet = registry.chain(1).unwrap();
+    assert_eq!(
+        mainnet.uniswap_v2_router,
+        address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D")
+    );
+    assert_eq!(mainnet.uniswap_v2_factories[0].name, "uniswap");
+    assert_eq!(registry.find_chain("Mainnet").unwrap(), mainnet);
+    assert_eq!(registry.find_chain("1").unwrap(), mainnet);
+}
+
+#[test]
+fn config_extends_and_overrides_the_defaults() {
+    let registry = Registry::from_toml(
+        r#"
+        caller = "0x00000000000000000000000000000000000000b0"
+
+        [[chains]]
+        chain_id = 1
+        name = "mainnet"
+        weth = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        multicall3 = "0xcA11bde05977b3631167028862bE2a173976CA11"
+        uniswap_v2_router = "0x00000000000000000000000000000000000000c0"
+        uniswap_v3_simulator = "0x1100000000000000000000000000000000000011"
+
+        [[chains]]
+        chain_id = 11155111
+        name = "sepolia"
+        weth = "0xfFf9976782d46CC05630D1f6eBAb18b2324d6B14"
+        multicall3 = "0xcA11bde05977b3631167028862bE2a173976CA11"
+        uniswap_v2_router = "0xeE567Fe1712Faf6149d80dA1E6934E354124CfE3"
+        uniswap_v3_simulator = "0x1100000000000000000000000000000000000011"
+
+        [[chains.uniswap_v2_factories]]
+        name = "uniswap"
+        address = "0xF62c03E08ada871A0bEb309762E260a7a6a880E6"
+        init_code_hash = "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        registry.caller,
+        address!("00000000000000000000000000000000000000b0")
+    );
+    assert_eq!(
+        registry.chain(1).unwrap().uniswap_v2_router,
+        address!("00000000000000000000000000000000000000c0")
+    );
+
+    let sepolia = registry.find_chain("sepolia").unwrap();
+    assert_eq!(sepolia.chain_id, 11155111);
+    assert_eq!(
+        sepolia.uniswap_v2_factories[0].init_code_hash,
+        b256!("96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f")
+    );
+}
+
+#[test]
+fn unknown_chains_are_configuration_errors() {
+    let registry = Registry::default();
+
+    assert!(matches!(
+        registry.chain(10),
+        Err(SimulationError::Config(_))
+    ));
+    assert!(matches!(
+        Registry::from_toml("chains = 1"),
+        Err(SimulationError::Config(_))
+    ));
+}
@@ -0,0 +1,132 @@
+use alloy_sol_types::{sol, Panic, Revert, SolError};
+use revm::primitives::{
+    address, AccessList, Address, Bytes, EvmState, ExecutionResult, HaltReason, OutOfGasError,
+    ResultAndState, U256,
+};
+use rust_revm_simulations::commons::{
+    decode_revert, describe_halt, ExecutionStatus, RevertReason, SimulationOutcome,
+};
+
+sol! {
+    error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed);
+}
+
+#[test]
+fn decodes_error_strings() {
+    let output = Revert::from("UniswapV2: K").abi_encode();
+
+    assert_eq!(
+        decode_revert(&output),
+        RevertReason::Message("UniswapV2: K".to_owned())
+    );
+}
+
+#[test]
+fn decodes_panics() {
+    let output = Panic::from(U256::from(0x11)).abi_encode();
+
+    let reason = decode_revert(&output);
+    assert_eq!(
+        reason.to_string(),
+        "panic 0x11: arithmetic underflow or overflow"
+    );
+}
+
+#[test]
+fn decodes_known_custom_errors() {
+    let output = ERC20InsufficientBalance {
+        sender: address!("FF3cF7b8582571095A2B05268A4E1BafBDAD060D"),
+        balance: U256::from(1),
+        needed: U256::from(2),
+    }
+    .abi_encode();
+
+    match decode_revert(&output) {
+        RevertReason::Custom { source, error } => {
+            assert_eq!(source, "ERC20");
+            assert!(error.starts_with("ERC20InsufficientBalance"));
+        }
+        reason => panic!("Unexpected reason {reason:?}"),
+    }
+}
+
+#[test]
+fn unwraps_the_uniswap_v3_simulator_payload() {
+    let pool_revert = Revert::from("IIA").abi_encode();
+    let message = [b"UNISWAP_V3 Revert: ".as_slice(), &pool_revert].concat();
+    let output = alloy_sol_types::SolValue::abi_encode(&Bytes::from(message));
+    let output = [Revert::SELECTOR.as_slice(), &output].concat();
+
+    let reason = decode_revert(&output);
+    assert_eq!(
+        reason,
+        RevertReason::UniswapV3Simulator(Box::new(RevertReason::Message("IIA".to_owned())))
+    );
+    assert_eq!(
+        reason.to_string(),
+        "Uniswap V3 pool reverted: IIA (insufficient input amount, the callback did not pay the pool)"
+    );
+}
+
+#[test]
+fn reports_empty_and_unknown_payloads() {
+    assert_eq!(decode_revert(&[]), RevertReason::Empty);
+    assert!(matches!(
+        decode_revert(&[0xde, 0xad, 0xbe, 0xef, 0x00]),
+        RevertReason::Unknown(_)
+    ));
+}
+
+#[test]
+fn describes_halts() {
+    assert_eq!(
+        describe_halt(&HaltReason::OutOfGas(OutOfGasError::Basic)),
+        "out of gas"
+    );
+    assert_eq!(describe_halt(&HaltReason::OpcodeNotFound), "invalid opcode");
+}
+
+#[test]
+fn failed_executions_have_an_outcome_with_their_reason() {
+    let output = Bytes::from(Revert::from("UniswapV2: K").abi_encode());
+    let reverted = ResultAndState {
+        result: ExecutionResult::Revert {
+            gas_used: 30_000,
+            output: output.clone(),
+        },
+        state: EvmState::default(),
+    };
+
+    let outcome = SimulationOutcome::from_execution(
+        &reverted,
+        Address::ZERO,
+        Bytes::new(),
+        AccessList::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        outcome.status,
+        ExecutionStatus::Revert(RevertReason::Message("UniswapV2: K".to_owned()))
+    );
+    assert_eq!(outcome.status.to_string(), "Reverted: UniswapV2: K");
+    assert_eq!(outcome.output, output);
+    assert_eq!(outcome.gas.gas_used, 30_000);
+
+    let halted = ResultAndState {
+        result: ExecutionResult::Halt {
+            reason: HaltReason::OpcodeNotFound,
+            gas_used: 50_000,
+        },
+        state: EvmState::default(),
+    };
+
+    let outcome = SimulationOutcome::from_execution(
+        &halted,
+        Address::ZERO,
+        Bytes::new(),
+        AccessList::default(),
+    )
+    .unwrap();
+    assert_eq!(outcome.status.to_string(), "Halted: invalid opcode");
+    assert_eq!(outcome.gas.gas_used, 50_000);
+}
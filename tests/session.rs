@@ -0,0 +1,170 @@
+mod common;
+
+use alloy_sol_types::{sol, SolCall};
+use common::{
+    block, in_memory_session, Assembler, MockErc20, SessionBuilder, RECIPIENT, SENDER, TOKEN,
+};
+use revm::interpreter::opcode::{BALANCE, BASEFEE, CHAINID, GAS, NUMBER, POP, RETURN, TIMESTAMP};
+use revm::primitives::{address, AccessList, Bytecode, Bytes, SpecId, U256};
+use revm::InMemoryDB;
+use rust_revm_simulations::commons::{
+    extract_gas_output_and_logs, set_code, set_eth_balance, spec_id_at, SimulationError,
+};
+use rust_revm_simulations::{ExecutionStrategy, SimulationSession};
+
+sol! {
+    function transfer(address destination, uint value) public returns (bool);
+}
+
+#[test]
+fn calls_see_the_block_environment() {
+    let probe = address!("00000000000000000000000000000000000000c0");
+    let mut asm = Assembler::default();
+    for (index, opcode) in [NUMBER, TIMESTAMP, BASEFEE, CHAINID]
+        .into_iter()
+        .enumerate()
+    {
+        asm.op(opcode).mstore_at(32 * index as u64);
+    }
+    asm.push(U256::from(128)).push(U256::ZERO).op(RETURN);
+
+    let mut session = in_memory_session();
+    set_code(probe, Bytecode::new_raw(asm.assemble()), session.database()).unwrap();
+
+    let result = session
+        .transact(probe, Bytes::default(), &AccessList::default())
+        .unwrap();
+    let (output, _, _, _) = extract_gas_output_and_logs(&result.result).unwrap();
+
+    let words = output
+        .chunks(32)
+        .map(U256::from_be_slice)
+        .collect::<Vec<_>>();
+    let header = block().header;
+    assert_eq!(
+        words,
+        vec![
+            U256::from(header.number),
+            U256::from(header.timestamp),
+            U256::from(header.base_fee_per_gas.unwrap_or_default()),
+            U256::from(session.cfg_env().chain_id),
+        ]
+    );
+}
+
+fn session_with_balance(strategy: ExecutionStrategy) -> (SimulationSession<InMemoryDB>, MockErc20) {
+    let token = MockErc20::default();
+    let session = SessionBuilder::default()
+        .token(TOKEN, token.clone())
+        .balance(TOKEN, SENDER, U256::from(1_000))
+        .build()
+        .with_strategy(strategy);
+
+    (session, token)
+}
+
+fn transfer_calldata() -> Bytes {
+    Bytes::from(transferCall::new((RECIPIENT, U256::from(400))).abi_encode())
+}
+
+#[test]
+fn dry_run_does_not_commit() {
+    let (mut session, token) = session_with_balance(ExecutionStrategy::DryRun);
+
+    let outcome = session
+        .execute(TOKEN, transfer_calldata(), |_| Ok(()))
+        .unwrap();
+
+    assert_eq!(outcome.logs.len(), 1);
+    assert_eq!(
+        revm::Database::storage(session.database(), TOKEN, token.balance_slot(SENDER)).unwrap(),
+        U256::from(1_000)
+    );
+}
+
+#[test]
+fn commit_executes_once() {
+    let (mut session, token) = session_with_balance(ExecutionStrategy::Commit);
+
+    let outcome = session
+        .execute(TOKEN, transfer_calldata(), |_| Ok(()))
+        .unwrap();
+
+    assert!(outcome.gas_with_access_list.is_none());
+    assert_eq!(
+        revm::Database::storage(session.database(), TOKEN, token.balance_slot(SENDER)).unwrap(),
+        U256::from(600)
+    );
+}
+
+#[test]
+fn two_pass_rejects_outputs_that_depend_on_the_access_list() {
+    // Reads the balance of a cold account and returns the gas left afterwards, which changes
+    // when the account is warmed up by the access list.
+    let probe = address!("00000000000000000000000000000000000000c0");
+    let mut asm = Assembler::default();
+    asm.push_bytes(address!("00000000000000000000000000000000000000dd").as_slice())
+        .op(BALANCE)
+        .op(POP)
+        .op(GAS)
+        .return_word();
+
+    let mut session = in_memory_session();
+    set_code(probe, Bytecode::new_raw(asm.assemble()), session.database()).unwrap();
+
+    let error = session
+        .execute(probe, Bytes::default(), |_| Ok(()))
+        .unwrap_err();
+    assert!(error.to_string().contains("differs"));
+
+    let outcome = session
+        .execute_with(ExecutionStrategy::Commit, probe, Bytes::default(), |_| {
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(outcome.access_list.len(), 1);
+}
+
+#[test]
+fn real_callers_pay_for_the_gas_with_their_own_balance() {
+    let (session, _) = session_with_balance(ExecutionStrategy::Commit);
+    let mut session = session.with_balance_check(true);
+
+    let error = session
+        .execute(TOKEN, transfer_calldata(), |_| Ok(()))
+        .unwrap_err();
+    assert!(matches!(error, SimulationError::Evm(_)));
+    assert!(error.to_string().contains("cannot pay for the gas"));
+
+    // Enough for the transfer, far below the gas limit of the block.
+    set_eth_balance(SENDER, U256::from(7 * 100_000), session.database()).unwrap();
+    session
+        .execute(TOKEN, transfer_calldata(), |_| Ok(()))
+        .unwrap();
+
+    let balance = revm::Database::basic(session.database(), SENDER)
+        .unwrap()
+        .unwrap()
+        .balance;
+    assert!(balance < U256::from(7 * 100_000));
+}
+
+#[test]
+fn the_hardfork_follows_the_forked_block() {
+    assert_eq!(spec_id_at(1, 9_100_000, 1_576_000_000), SpecId::ISTANBUL);
+    assert_eq!(spec_id_at(1, 16_000_000, 1_670_000_000), SpecId::MERGE);
+    assert_eq!(spec_id_at(1, 19_500_000, 1_711_000_000), SpecId::CANCUN);
+    assert_eq!(spec_id_at(1, 22_500_000, 1_748_000_000), SpecId::PRAGUE);
+    assert_eq!(
+        spec_id_at(11_155_111, 5_000_000, 1_705_000_000),
+        SpecId::SHANGHAI
+    );
+    assert_eq!(spec_id_at(56, 40_000_000, 1_720_000_000), SpecId::CANCUN);
+
+    let session = in_memory_session();
+    assert_eq!(session.spec_id(), SpecId::SHANGHAI);
+    assert_eq!(
+        session.with_spec_id(SpecId::CANCUN).spec_id(),
+        SpecId::CANCUN
+    );
+}
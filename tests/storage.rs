@@ -0,0 +1,249 @@
+mod common;
+
+use alloy_sol_types::SolCall;
+use common::{in_memory_session, Assembler, MappingLayout, MockErc20, RECIPIENT, SENDER, TOKEN};
+use revm::interpreter::opcode::*;
+use revm::primitives::{address, keccak256, Address, Bytecode, Bytes, B256, U256};
+use revm::Database;
+use rust_revm_simulations::commons::{
+    set_code, set_storage, HashOrder, SimulationError, StorageMapping,
+};
+use rust_revm_simulations::Erc20;
+
+alloy_sol_types::sol! {
+    function balanceOf(address account) external returns (uint256);
+    function allowance(address owner, address spender) external returns (uint256);
+}
+
+const IMPLEMENTATION: Address = address!("00000000000000000000000000000000000000d0");
+
+// ERC-7201 slot of the `example.token` namespace.
+fn namespace() -> U256 {
+    let namespace = U256::from_be_bytes(keccak256("example.token").0) - U256::from(1);
+    U256::from_be_bytes(keccak256(namespace.to_be_bytes::<32>()).0) & !U256::from(0xff)
+}
+
+// Token whose balances are namespaced and packed as `uint128` above 96 bits of other data.
+fn packed_token_bytecode() -> Bytes {
+    let mut asm = Assembler::default();
+    asm.calldata_word(0)
+        .mstore_at(0)
+        .push(namespace())
+        .mstore_at(32)
+        .push(U256::from(64))
+        .push(U256::ZERO)
+        .op(KECCAK256)
+        .op(SLOAD)
+        .push(U256::from(96))
+        .op(SHR)
+        .push(U256::MAX >> 128)
+        .op(AND)
+        .return_word();
+
+    asm.assemble()
+}
+
+// Proxy delegating every call to the implementation.
+fn proxy_bytecode() -> Bytes {
+    let mut asm = Assembler::default();
+    asm.op(CALLDATASIZE)
+        .push(U256::ZERO)
+        .push(U256::ZERO)
+        .op(CALLDATACOPY)
+        .push(U256::ZERO)
+        .push(U256::ZERO)
+        .op(CALLDATASIZE)
+        .push(U256::ZERO)
+        .push_bytes(IMPLEMENTATION.as_slice())
+        .op(GAS)
+        .op(DELEGATECALL)
+        .op(RETURNDATASIZE)
+        .push(U256::ZERO)
+        .push(U256::ZERO)
+        .op(RETURNDATACOPY)
+        .op(ISZERO)
+        .jumpi("revert")
+        .op(RETURNDATASIZE)
+        .push(U256::ZERO)
+        .op(RETURN)
+        .label("revert")
+        .op(RETURNDATASIZE)
+        .push(U256::ZERO)
+        .op(REVERT);
+
+    asm.assemble()
+}
+
+#[test]
+fn balance_slot_of_vyper_tokens_is_found() {
+    let mut session = in_memory_session();
+    let mock = MockErc20 {
+        layout: MappingLayout::Vyper,
+        ..MockErc20::default()
+    };
+    mock.deploy(TOKEN, session.database());
+    let token = Erc20::new(TOKEN);
+
+    token
+        .set_balance(SENDER, U256::from(1_000), &mut session)
+        .unwrap();
+
+    assert_eq!(
+        token.balance_of(SENDER, &mut session).unwrap().decoded,
+        U256::from(1_000)
+    );
+    assert_eq!(
+        session
+            .database()
+            .storage(TOKEN, mock.balance_slot(SENDER))
+            .unwrap(),
+        U256::from(1_000)
+    );
+
+    let mapping = session
+        .storage_mapping(TOKEN, balanceOfCall::SELECTOR)
+        .unwrap();
+    assert_eq!(mapping.order, HashOrder::SlotFirst);
+    assert_eq!(mapping.base, B256::from(mock.balances_slot));
+}
+
+#[test]
+fn packed_namespaced_balances_keep_the_other_bits() {
+    let mut session = in_memory_session();
+    set_code(
+        TOKEN,
+        Bytecode::new_raw(packed_token_bytecode()),
+        session.database(),
+    )
+    .unwrap();
+
+    let mapping = StorageMapping {
+        storage: TOKEN,
+        base: B256::from(namespace()),
+        order: HashOrder::KeyFirst,
+        shift: 96,
+        width: 128,
+    };
+    let slot = mapping.slot(&[SENDER.into_word()]);
+    let other_bits = (U256::from(0xabc) << 224) | U256::from(0x123);
+    set_storage(TOKEN, slot, other_bits, session.database()).unwrap();
+
+    let token = Erc20::new(TOKEN);
+    token
+        .set_balance(SENDER, U256::from(1_000), &mut session)
+        .unwrap();
+
+    assert_eq!(
+        session.storage_mapping(TOKEN, balanceOfCall::SELECTOR),
+        Some(mapping)
+    );
+    assert_eq!(
+        token.balance_of(SENDER, &mut session).unwrap().decoded,
+        U256::from(1_000)
+    );
+    assert_eq!(
+        session.database().storage(TOKEN, slot).unwrap(),
+        other_bits | (U256::from(1_000) << 96)
+    );
+
+    let error = token
+        .set_balance(SENDER, U256::MAX, &mut session)
+        .unwrap_err();
+    assert!(matches!(error, SimulationError::Config(_)));
+}
+
+#[test]
+fn balance_slot_of_proxies_is_in_the_proxy_storage() {
+    let mut session = in_memory_session();
+    let mock = MockErc20::default();
+    mock.deploy(IMPLEMENTATION, session.database());
+    set_code(
+        TOKEN,
+        Bytecode::new_raw(proxy_bytecode()),
+        session.database(),
+    )
+    .unwrap();
+    let token = Erc20::new(TOKEN);
+
+    token
+        .set_balance(SENDER, U256::from(1_000), &mut session)
+        .unwrap();
+
+    assert_eq!(
+        token.balance_of(SENDER, &mut session).unwrap().decoded,
+        U256::from(1_000)
+    );
+    assert_eq!(
+        session
+            .database()
+            .storage(TOKEN, mock.balance_slot(SENDER))
+            .unwrap(),
+        U256::from(1_000)
+    );
+}
+
+#[test]
+fn tokens_without_balance_slot_are_reported() {
+    let mut session = in_memory_session();
+    let mut asm = Assembler::default();
+    asm.push(U256::from(42)).return_word();
+    set_code(TOKEN, Bytecode::new_raw(asm.assemble()), session.database()).unwrap();
+
+    let error = Erc20::new(TOKEN)
+        .set_balance(SENDER, U256::from(1_000), &mut session)
+        .unwrap_err();
+
+    assert!(matches!(error, SimulationError::InvariantViolation(_)));
+}
+
+#[test]
+fn allowance_is_written_in_the_nested_mapping() {
+    for (layout, order) in [
+        (MappingLayout::Solidity, HashOrder::KeyFirst),
+        (MappingLayout::Vyper, HashOrder::SlotFirst),
+    ] {
+        let mut session = in_memory_session();
+        let mock = MockErc20 {
+            layout,
+            ..MockErc20::default()
+        };
+        mock.deploy(TOKEN, session.database());
+        let token = Erc20::new(TOKEN);
+
+        token
+            .set_allowance(SENDER, RECIPIENT, U256::from(500), &mut session)
+            .unwrap();
+
+        assert_eq!(
+            token
+                .allowance(SENDER, RECIPIENT, &mut session)
+                .unwrap()
+                .decoded,
+            U256::from(500)
+        );
+
+        let owner = SENDER.into_word();
+        let spender = RECIPIENT.into_word();
+        let base = B256::from(mock.allowances_slot);
+        let slot = match layout {
+            MappingLayout::Solidity => {
+                let inner = keccak256([owner.as_slice(), base.as_slice()].concat());
+                keccak256([spender.as_slice(), inner.as_slice()].concat())
+            }
+            MappingLayout::Vyper => {
+                let inner = keccak256([base.as_slice(), owner.as_slice()].concat());
+                keccak256([inner.as_slice(), spender.as_slice()].concat())
+            }
+        };
+        assert_eq!(
+            session.database().storage(TOKEN, slot.into()).unwrap(),
+            U256::from(500)
+        );
+
+        let mapping = session
+            .storage_mapping(TOKEN, allowanceCall::SELECTOR)
+            .unwrap();
+        assert_eq!(mapping.order, order);
+        assert_eq!(mapping.base, base);
+    }
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::{reserve, MockErc20, MockPair, SessionBuilder, RECIPIENT, TOKEN};
+use revm::primitives::{address, Address, U256};
+use revm::InMemoryDB;
+use rust_revm_simulations::commons::SimulationError;
+use rust_revm_simulations::token_safety::{check_token, TokenSafetyConfig, TradingPool};
+use rust_revm_simulations::uniswap_v2::v2_math::{get_amount_out, SwapFee};
+use rust_revm_simulations::SimulationSession;
+
+const WETH: Address = address!("00000000000000000000000000000000000000a1");
+const PAIR: Address = address!("00000000000000000000000000000000000000c0");
+
+fn config() -> TokenSafetyConfig {
+    TokenSafetyConfig::new(
+        TOKEN,
+        TradingPool::UniswapV2(PAIR),
+        U256::from(10).pow(U256::from(18)),
+    )
+    .with_recipient(RECIPIENT)
+}
+
+fn session_with_token(token: MockErc20) -> SimulationSession<InMemoryDB> {
+    session_with_pair(token, MockPair::new(WETH, TOKEN))
+}
+
+fn session_with_pair(token: MockErc20, pair: MockPair) -> SimulationSession<InMemoryDB> {
+    SessionBuilder::default()
+        .token(TOKEN, token)
+        .token(WETH, MockErc20::default())
+        .pair(PAIR, pair, reserve(), reserve())
+        .build()
+}
+
+#[test]
+fn taxes_are_reported_for_every_trade() {
+    let mut session = session_with_token(MockErc20 {
+        transfer_tax_bps: 300,
+        ..MockErc20::default()
+    });
+
+    let report = check_token(&mut session, &config()).unwrap();
+
+    assert_eq!(report.base_token, WETH);
+    assert!(report.is_tradable());
+    for check in [
+        &report.buy,
+        report.transfer.as_ref().unwrap(),
+        report.sell.as_ref().unwrap(),
+    ] {
+        let tax = check.tax.unwrap();
+        assert_eq!(tax.amount(), tax.sent * U256::from(3) / U256::from(100));
+    }
+}
+
+#[test]
+fn tokens_that_cannot_be_sold_are_not_tradable() {
+    let mut session = session_with_token(MockErc20 {
+        blocked_recipient: Some(PAIR),
+        ..MockErc20::default()
+    });
+
+    let report = check_token(&mut session, &config()).unwrap();
+
+    assert!(report.buy.is_success());
+    assert!(report.transfer.as_ref().unwrap().is_success());
+    assert!(report.sell.as_ref().unwrap().error.is_some());
+    assert!(!report.is_tradable());
+}
+
+#[test]
+fn limits_and_blacklists_are_read_from_the_token() {
+    let mut session = session_with_token(MockErc20 {
+        getters: vec![
+            ("_maxTxAmount()", U256::from(5_000)),
+            ("isBot(address)", U256::from(1)),
+        ],
+        ..MockErc20::default()
+    });
+
+    let report = check_token(&mut session, &config()).unwrap();
+
+    assert_eq!(report.limits.len(), 1);
+    assert_eq!(report.limits[0].getter, "_maxTxAmount()");
+    assert_eq!(report.limits[0].value, U256::from(5_000));
+    assert_eq!(report.blacklisted_by, vec!["isBot(address)".to_owned()]);
+}
+
+#[test]
+fn pools_not_trading_the_token_are_refused() {
+    let mut session = session_with_token(MockErc20::default());
+
+    let config = TokenSafetyConfig::new(
+        address!("00000000000000000000000000000000000000a2"),
+        TradingPool::UniswapV2(PAIR),
+        U256::from(1_000),
+    );
+
+    assert!(matches!(
+        check_token(&mut session, &config),
+        Err(SimulationError::Config(_))
+    ));
+}
+
+#[test]
+fn pairs_are_traded_with_their_own_fee() {
+    let pair = MockPair {
+        fee: 25,
+        fee_denominator: 10_000,
+        ..MockPair::new(WETH, TOKEN)
+    };
+    let mut session = session_with_pair(MockErc20::default(), pair);
+    let fee = SwapFee::new(25, 10_000).unwrap();
+
+    let report = check_token(&mut session, &config().with_fee(fee)).unwrap();
+
+    assert_eq!(
+        report.buy.amount_out,
+        get_amount_out(config().amount, reserve(), reserve(), fee).unwrap()
+    );
+    assert!(report.is_tradable());
+}
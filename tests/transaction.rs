@@ -0,0 +1,87 @@
+mod common;
+
+use alloy_consensus::{SignableTransaction, TxEnvelope};
+use alloy_eips::eip2718::Decodable2718;
+use common::{block, MockErc20, RECIPIENT, TOKEN};
+use revm::primitives::{address, TxKind, U256};
+use rust_revm_simulations::commons::{sign_steps, Identity, SimulationError, TransactionSettings};
+use rust_revm_simulations::{Erc20, SimulationSession};
+
+// First development account of anvil and hardhat.
+const PRIVATE_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+#[test]
+fn steps_are_signed_as_eip1559_transactions() {
+    let identity = Identity::from_private_key(PRIVATE_KEY).unwrap();
+    let signer = identity.signer().unwrap();
+    let mut session = SimulationSession::in_memory(block(), identity.address());
+    MockErc20::default().deploy(TOKEN, session.database());
+
+    let token = Erc20::new(TOKEN);
+    token
+        .set_balance(identity.address(), U256::from(1_000), &mut session)
+        .unwrap();
+    let nonce = session.nonce(identity.address()).unwrap();
+    let transfer = token
+        .transfer(RECIPIENT, U256::from(400), &mut session)
+        .unwrap();
+    let gas_spent = transfer.gas.gas_used + transfer.gas.gas_refunded;
+
+    let settings = TransactionSettings::default();
+    let transactions = sign_steps(
+        &session,
+        &[transfer.into_step("ERC20 Transfer")],
+        nonce,
+        &settings,
+        signer,
+    )
+    .unwrap();
+
+    assert_eq!(transactions.len(), 1);
+    let transaction = &transactions[0];
+    assert_eq!(transaction.name, "ERC20 Transfer");
+    assert_eq!(transaction.gas_limit, gas_spent + gas_spent / 5);
+    // The next base fee stays at 7 wei as the block is empty but the decrease rounds down.
+    assert_eq!(
+        transaction.max_fee_per_gas,
+        14 + settings.max_priority_fee_per_gas
+    );
+
+    let TxEnvelope::Eip1559(signed) =
+        TxEnvelope::decode_2718(&mut transaction.raw.as_ref()).unwrap()
+    else {
+        panic!("Not an EIP-1559 transaction");
+    };
+    assert_eq!(*signed.hash(), transaction.hash);
+    assert_eq!(signed.tx().chain_id, 1);
+    assert_eq!(signed.tx().nonce, nonce);
+    assert_eq!(signed.tx().to, TxKind::Call(TOKEN));
+    assert_eq!(signed.tx().input, transaction.input);
+    assert_eq!(
+        signed
+            .signature()
+            .recover_address_from_prehash(&signed.tx().signature_hash())
+            .unwrap(),
+        identity.address()
+    );
+}
+
+#[test]
+fn signer_must_be_the_caller() {
+    let identity = Identity::from_private_key(PRIVATE_KEY).unwrap();
+    let session = SimulationSession::in_memory(
+        block(),
+        address!("00000000000000000000000000000000000000c0"),
+    );
+
+    let error = sign_steps(
+        &session,
+        &[],
+        0,
+        &TransactionSettings::default(),
+        identity.signer().unwrap(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(error, SimulationError::Config(_)));
+}
@@ -0,0 +1,366 @@
+mod common;
+
+use common::{amount, reserve, MockErc20, MockFactory, MockPair, SessionBuilder, SENDER, TOKEN};
+use revm::primitives::{address, Address, B256, U256};
+use revm::InMemoryDB;
+use rust_revm_simulations::commons::{FactoryConfig, Registry, SimulationError};
+use rust_revm_simulations::uniswap_v2::contracts::Pool;
+use rust_revm_simulations::uniswap_v2::pair::{
+    compute_pair_address, find_pair, sort_tokens, verify_pair,
+};
+use rust_revm_simulations::uniswap_v2::swap_via_pool::{
+    get_input_amount, simulate, SwapViaPoolConfig,
+};
+use rust_revm_simulations::uniswap_v2::swap_via_router::{
+    simulate_with_pool, RouterSwap, SwapViaRouterConfig,
+};
+use rust_revm_simulations::uniswap_v2::v2_math::SwapFee;
+use rust_revm_simulations::{Erc20, SimulationSession};
+
+const OTHER_TOKEN: Address = address!("00000000000000000000000000000000000000a1");
+const THIRD_TOKEN: Address = address!("00000000000000000000000000000000000000a2");
+const PAIR: Address = address!("00000000000000000000000000000000000000c0");
+const SECOND_PAIR: Address = address!("00000000000000000000000000000000000000c1");
+const ROUTER: Address = address!("00000000000000000000000000000000000000d0");
+const FACTORY: Address = address!("00000000000000000000000000000000000000e0");
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+fn session_with_pair(tax_in_bps: u64, tax_out_bps: u64) -> SimulationSession<InMemoryDB> {
+    let taxed = |transfer_tax_bps| MockErc20 {
+        transfer_tax_bps,
+        ..MockErc20::default()
+    };
+
+    SessionBuilder::default()
+        .token(TOKEN, taxed(tax_in_bps))
+        .token(OTHER_TOKEN, taxed(tax_out_bps))
+        .pair(
+            PAIR,
+            MockPair::new(TOKEN, OTHER_TOKEN),
+            reserve(),
+            reserve(),
+        )
+        .build()
+}
+
+// getAmountOut with the 0.3% fee, on equal reserves.
+fn amount_out(amount_in: U256) -> U256 {
+    let amount_in_with_fee = amount_in * U256::from(997);
+    amount_in_with_fee * reserve() / (reserve() * U256::from(1000) + amount_in_with_fee)
+}
+
+#[test]
+fn swap_without_taxes_reports_zero_taxes() {
+    let mut session = session_with_pair(0, 0);
+
+    let result = simulate(&mut session, &SwapViaPoolConfig::new(PAIR, TOKEN, amount())).unwrap();
+
+    assert_eq!(result.token_out, OTHER_TOKEN);
+    assert_eq!(result.amount_out, amount_out(amount()));
+    assert_eq!(result.input_tax.unwrap().amount(), U256::ZERO);
+    assert_eq!(result.output_tax.unwrap().amount(), U256::ZERO);
+    assert!(result
+        .to_string()
+        .contains("Amount in: 1000000000000000000000 (1000 MOCK)"));
+}
+
+#[test]
+fn swap_computes_the_output_from_the_amount_the_pool_received() {
+    let mut session = session_with_pair(500, 0);
+
+    let result = simulate(&mut session, &SwapViaPoolConfig::new(PAIR, TOKEN, amount())).unwrap();
+
+    let input_tax = result.input_tax.unwrap();
+    let received = amount() * U256::from(95) / U256::from(100);
+    assert_eq!(input_tax.received, received);
+    assert_eq!(input_tax.basis_points(), U256::from(500));
+    assert_eq!(result.amount_in, amount());
+    assert_eq!(result.amount_out, amount_out(received));
+    assert_eq!(
+        Erc20::new(OTHER_TOKEN)
+            .balance_of(SENDER, &mut session)
+            .unwrap()
+            .decoded,
+        result.amount_out
+    );
+}
+
+#[test]
+fn swap_reports_the_tax_taken_on_the_output() {
+    let mut session = session_with_pair(0, 1000);
+
+    let result = simulate(&mut session, &SwapViaPoolConfig::new(PAIR, TOKEN, amount())).unwrap();
+
+    let output_tax = result.output_tax.unwrap();
+    assert_eq!(output_tax.sent, amount_out(amount()));
+    assert_eq!(output_tax.amount(), output_tax.sent / U256::from(10));
+    assert_eq!(result.amount_out, output_tax.received);
+    assert_eq!(
+        Erc20::new(OTHER_TOKEN)
+            .balance_of(SENDER, &mut session)
+            .unwrap()
+            .decoded,
+        result.amount_out
+    );
+}
+
+#[test]
+fn swap_reports_the_taxes_taken_on_both_legs() {
+    let mut session = session_with_pair(500, 1000);
+
+    let result = simulate(&mut session, &SwapViaPoolConfig::new(PAIR, TOKEN, amount())).unwrap();
+
+    let input_tax = result.input_tax.unwrap();
+    let output_tax = result.output_tax.unwrap();
+    assert_eq!(input_tax.basis_points(), U256::from(500));
+    assert_eq!(output_tax.sent, amount_out(input_tax.received));
+    assert_eq!(output_tax.amount(), output_tax.sent / U256::from(10));
+}
+
+#[test]
+fn exact_output_swap_receives_the_requested_amount() {
+    let mut session = session_with_pair(0, 0);
+    let amount_in =
+        get_input_amount(PAIR, TOKEN, amount(), SwapFee::UNISWAP, &mut session).unwrap();
+
+    let result = simulate(
+        &mut session,
+        &SwapViaPoolConfig::new(PAIR, TOKEN, amount()).with_exact_out(true),
+    )
+    .unwrap();
+
+    assert_eq!(result.amount_in, amount_in);
+    assert_eq!(result.amount_out, amount());
+    assert_eq!(
+        Erc20::new(OTHER_TOKEN)
+            .balance_of(SENDER, &mut session)
+            .unwrap()
+            .decoded,
+        amount()
+    );
+}
+
+#[test]
+fn input_amount_gives_the_output() {
+    let mut session = session_with_pair(0, 0);
+
+    let amount_in =
+        get_input_amount(PAIR, TOKEN, amount(), SwapFee::UNISWAP, &mut session).unwrap();
+
+    assert!(amount_out(amount_in) >= amount());
+    assert!(amount_out(amount_in - U256::from(2)) < amount());
+}
+
+#[test]
+fn exact_output_fails_when_the_token_in_takes_a_fee() {
+    let mut session = session_with_pair(500, 0);
+
+    let error = simulate(
+        &mut session,
+        &SwapViaPoolConfig::new(PAIR, TOKEN, amount()).with_exact_out(true),
+    )
+    .unwrap_err();
+
+    assert!(matches!(error, SimulationError::InvariantViolation(_)));
+}
+
+#[test]
+fn tokens_not_traded_by_the_pair_are_refused() {
+    let mut session = session_with_pair(0, 0);
+
+    let error = simulate(
+        &mut session,
+        &SwapViaPoolConfig::new(PAIR, THIRD_TOKEN, amount()),
+    )
+    .unwrap_err();
+    assert!(matches!(error, SimulationError::Config(_)));
+
+    let error = get_input_amount(
+        PAIR,
+        THIRD_TOKEN,
+        amount(),
+        SwapFee::default(),
+        &mut session,
+    )
+    .unwrap_err();
+    assert!(matches!(error, SimulationError::Config(_)));
+}
+
+// TOKEN -> OTHER_TOKEN -> THIRD_TOKEN through two pairs with equal reserves.
+fn session_with_route() -> SimulationSession<InMemoryDB> {
+    SessionBuilder::default()
+        .token(TOKEN, MockErc20::default())
+        .token(OTHER_TOKEN, MockErc20::default())
+        .token(THIRD_TOKEN, MockErc20::default())
+        .pair(
+            PAIR,
+            MockPair::new(TOKEN, OTHER_TOKEN),
+            reserve(),
+            reserve(),
+        )
+        .pair(
+            SECOND_PAIR,
+            MockPair::new(OTHER_TOKEN, THIRD_TOKEN),
+            reserve(),
+            reserve(),
+        )
+        .build()
+}
+
+#[test]
+fn multi_hop_swaps_are_replayed_pool_to_pool() {
+    // Stands in for the router, emitting a Swap event per hop.
+    let mut session = session_with_route();
+    let first_out = amount_out(amount());
+    let last_out = amount_out(first_out);
+    Erc20::new(TOKEN)
+        .set_balance(SENDER, amount(), &mut session)
+        .unwrap();
+    Erc20::new(TOKEN)
+        .transfer(PAIR, amount(), &mut session)
+        .unwrap();
+    let first_swap = Pool::new(PAIR)
+        .swap(U256::ZERO, first_out, SECOND_PAIR, &mut session)
+        .unwrap();
+    let mut swap = Pool::new(SECOND_PAIR)
+        .swap(U256::ZERO, last_out, SENDER, &mut session)
+        .unwrap()
+        .map(|_| vec![amount(), first_out, last_out]);
+    swap.logs = [first_swap.logs, swap.logs].concat();
+    swap.gas.gas_used = 1_000_000;
+    let approval = Erc20::new(TOKEN)
+        .approve(ROUTER, amount(), &mut session)
+        .unwrap();
+    let approval_gas = approval.gas.gas_used;
+    let router_swap = RouterSwap {
+        approvals: vec![approval],
+        swap,
+    };
+
+    let mut session = session_with_route();
+    let config =
+        SwapViaRouterConfig::new(ROUTER, TOKEN, THIRD_TOKEN, amount()).with_via(vec![OTHER_TOKEN]);
+    let result = simulate_with_pool(&mut session, &router_swap, &config).unwrap();
+
+    assert_eq!(config.path(), vec![TOKEN, OTHER_TOKEN, THIRD_TOKEN]);
+    assert_eq!(result.route, vec![PAIR, SECOND_PAIR]);
+    assert_eq!(result.steps.len(), 3);
+    assert_eq!(result.amount_in, amount());
+    assert_eq!(result.amount_out, last_out);
+    let gas_used = result
+        .steps
+        .iter()
+        .map(|step| step.outcome.gas.gas_used)
+        .sum::<u64>();
+    assert_eq!(
+        result.gas_saved,
+        Some(1_000_000 + approval_gas as i64 - gas_used as i64)
+    );
+    assert_eq!(
+        Erc20::new(THIRD_TOKEN)
+            .balance_of(SENDER, &mut session)
+            .unwrap()
+            .decoded,
+        last_out
+    );
+    assert_eq!(
+        Erc20::new(OTHER_TOKEN)
+            .balance_of(SENDER, &mut session)
+            .unwrap()
+            .decoded,
+        U256::ZERO
+    );
+}
+
+#[test]
+fn pair_addresses_are_computed_offline() {
+    let registry = Registry::default();
+    let mainnet = registry.chain(1).unwrap();
+
+    let uniswap = mainnet.uniswap_v2_factory("uniswap").unwrap();
+    let sushiswap = mainnet.uniswap_v2_factory("SushiSwap").unwrap();
+
+    assert_eq!(
+        compute_pair_address(uniswap, WETH, USDC).unwrap(),
+        address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc")
+    );
+    assert_eq!(
+        compute_pair_address(sushiswap, USDC, WETH).unwrap(),
+        compute_pair_address(sushiswap, WETH, USDC).unwrap()
+    );
+    assert_ne!(
+        compute_pair_address(sushiswap, USDC, WETH).unwrap(),
+        compute_pair_address(uniswap, USDC, WETH).unwrap()
+    );
+    assert!(mainnet.uniswap_v2_factory("unknown").is_err());
+    assert!(sort_tokens(USDC, USDC).is_err());
+}
+
+#[test]
+fn pairs_are_found_in_the_fork() {
+    let mut session = session_with_pair(0, 0);
+    MockFactory { pair: PAIR }.deploy(FACTORY, session.database());
+    let factory = FactoryConfig {
+        name: "mock".to_owned(),
+        address: FACTORY,
+        init_code_hash: B256::ZERO,
+        fee: SwapFee::default(),
+    };
+
+    // Nothing is deployed at the computed address, hence the factory is asked.
+    let pair = find_pair(&mut session, &factory, TOKEN, OTHER_TOKEN).unwrap();
+    assert_eq!(pair, PAIR);
+
+    MockFactory {
+        pair: Address::ZERO,
+    }
+    .deploy(FACTORY, session.database());
+    let error = find_pair(&mut session, &factory, TOKEN, OTHER_TOKEN).unwrap_err();
+    assert!(matches!(error, SimulationError::Config(_)));
+}
+
+#[test]
+fn only_genuine_pairs_are_verified() {
+    let mut session = session_with_pair(0, 0);
+    let factory = FactoryConfig {
+        name: "mock".to_owned(),
+        address: FACTORY,
+        init_code_hash: B256::repeat_byte(0x11),
+        fee: SwapFee::default(),
+    };
+    let factories = [factory.clone()];
+    let genuine = compute_pair_address(&factory, TOKEN, OTHER_TOKEN).unwrap();
+    MockPair {
+        factory: Some(FACTORY),
+        ..MockPair::new(TOKEN, OTHER_TOKEN)
+    }
+    .deploy(genuine, session.database());
+
+    let pool_data = Pool::new(genuine).get_pool_data(&mut session).unwrap();
+    assert_eq!(
+        verify_pair(genuine, &pool_data, &factories).unwrap(),
+        &factory
+    );
+
+    // The same code anywhere else is an impostor.
+    MockPair {
+        factory: Some(FACTORY),
+        ..MockPair::new(TOKEN, OTHER_TOKEN)
+    }
+    .deploy(PAIR, session.database());
+    let pool_data = Pool::new(PAIR).get_pool_data(&mut session).unwrap();
+    assert!(matches!(
+        verify_pair(PAIR, &pool_data, &factories),
+        Err(SimulationError::Config(_))
+    ));
+
+    // Deployed by a factory nobody knows.
+    assert!(verify_pair(genuine, &pool_data, &[]).is_err());
+
+    // Without `factory`.
+    MockPair::new(TOKEN, OTHER_TOKEN).deploy(PAIR, session.database());
+    let pool_data = Pool::new(PAIR).get_pool_data(&mut session).unwrap();
+    assert_eq!(pool_data.factory, None);
+    assert!(verify_pair(PAIR, &pool_data, &factories).is_err());
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{in_memory_session, MockErc20};
+use revm::primitives::{address, Address, Bytecode};
+use rust_revm_simulations::commons::{set_code, Registry, SimulationError};
+use rust_revm_simulations::uniswap_v3::contracts::PoolData;
+use rust_revm_simulations::uniswap_v3::pool::{compute_pool_address, verify_pool};
+use rust_revm_simulations::uniswap_v3::swap_via_pool::{
+    deploy_simulator, ensure_simulator_deployed,
+};
+
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+const USDC_WETH_500: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+const SIMULATOR: Address = address!("00000000000000000000000000000000000000f0");
+
+fn usdc_weth(factory: Address, fee: u128) -> PoolData {
+    PoolData {
+        factory,
+        token_0: USDC,
+        token_1: WETH,
+        fee,
+    }
+}
+
+#[test]
+fn pool_addresses_are_computed_offline() {
+    let registry = Registry::default();
+    let factory = &registry.chain(1).unwrap().uniswap_v3_factories[0];
+
+    assert_eq!(
+        compute_pool_address(factory, WETH, USDC, 500).unwrap(),
+        USDC_WETH_500
+    );
+    assert_ne!(
+        compute_pool_address(factory, WETH, USDC, 3000).unwrap(),
+        USDC_WETH_500
+    );
+}
+
+#[test]
+fn only_genuine_pools_are_verified() {
+    let registry = Registry::default();
+    let factories = &registry.chain(1).unwrap().uniswap_v3_factories;
+    let factory = factories[0].address;
+
+    assert_eq!(
+        verify_pool(USDC_WETH_500, &usdc_weth(factory, 500), factories)
+            .unwrap()
+            .name,
+        "uniswap"
+    );
+
+    // A pool lying about its fee tier, or about its factory.
+    for pool_data in [usdc_weth(factory, 3000), usdc_weth(WETH, 500)] {
+        assert!(matches!(
+            verify_pool(USDC_WETH_500, &pool_data, factories),
+            Err(SimulationError::Config(_))
+        ));
+    }
+}
+
+#[test]
+fn only_the_simulator_code_is_accepted_on_chain() {
+    let mut session = in_memory_session();
+    let missing = ensure_simulator_deployed(SIMULATOR, session.database()).unwrap_err();
+    assert!(missing.to_string().contains("is not deployed"));
+
+    let impostor = Bytecode::new_raw(MockErc20::default().bytecode());
+    set_code(SIMULATOR, impostor, session.database()).unwrap();
+    let error = ensure_simulator_deployed(SIMULATOR, session.database()).unwrap_err();
+    assert!(matches!(error, SimulationError::Config(_)));
+    assert!(error
+        .to_string()
+        .contains("is not the Uniswap V3 simulator"));
+
+    deploy_simulator(SIMULATOR, session.database()).unwrap();
+    ensure_simulator_deployed(SIMULATOR, session.database()).unwrap();
+}
@@ -0,0 +1,39 @@
+use revm::primitives::U256;
+use rust_revm_simulations::commons::{format_units, parse_units, SimulationError};
+
+#[test]
+fn amounts_in_token_units_are_scaled_by_the_decimals() {
+    assert_eq!(parse_units("1.5", 6).unwrap(), U256::from(1_500_000));
+    assert_eq!(parse_units("0.000001", 6).unwrap(), U256::from(1));
+    assert_eq!(parse_units(".5", 1).unwrap(), U256::from(5));
+    assert_eq!(parse_units("2.", 2).unwrap(), U256::from(200));
+    assert_eq!(parse_units("0.0", 18).unwrap(), U256::ZERO);
+    assert_eq!(
+        parse_units("1.25", 18).unwrap(),
+        U256::from(1_250_000_000_000_000_000u64)
+    );
+}
+
+#[test]
+fn invalid_amounts_are_refused() {
+    for amount in ["", ".", "1.2.3", "-1.5", "1,5", "0x1.5"] {
+        assert!(
+            matches!(parse_units(amount, 18), Err(SimulationError::Config(_))),
+            "{amount}"
+        );
+    }
+    assert!(matches!(
+        parse_units("1.0000001", 6),
+        Err(SimulationError::Config(_))
+    ));
+}
+
+#[test]
+fn raw_amounts_are_formatted_without_trailing_zeros() {
+    assert_eq!(format_units(U256::from(1_500_000), 6), "1.5");
+    assert_eq!(format_units(U256::from(1), 6), "0.000001");
+    assert_eq!(format_units(U256::from(2_000_000), 6), "2");
+    assert_eq!(format_units(U256::ZERO, 18), "0");
+    assert_eq!(format_units(U256::from(42), 0), "42");
+    assert_eq!(format_units(U256::MAX, 255).len(), 257);
+}
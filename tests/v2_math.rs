@@ -0,0 +1,116 @@
+mod common;
+
+use common::{MockErc20, MockPair, SessionBuilder, SENDER, TOKEN};
+use proptest::prelude::*;
+use revm::primitives::{address, Address, U256};
+use revm::InMemoryDB;
+use rust_revm_simulations::commons::SimulationError;
+use rust_revm_simulations::uniswap_v2::contracts::Pool;
+use rust_revm_simulations::uniswap_v2::v2_math::{get_amount_in, get_amount_out, SwapFee};
+use rust_revm_simulations::{Erc20, SimulationSession};
+
+const OTHER_TOKEN: Address = address!("00000000000000000000000000000000000000a1");
+const PAIR: Address = address!("00000000000000000000000000000000000000c0");
+
+const PANCAKESWAP: SwapFee = SwapFee {
+    numerator: 25,
+    denominator: 10000,
+};
+
+fn session_with_pair(
+    fee: SwapFee,
+    reserve_0: U256,
+    reserve_1: U256,
+) -> SimulationSession<InMemoryDB> {
+    let pair = MockPair {
+        fee: fee.numerator,
+        fee_denominator: fee.denominator,
+        ..MockPair::new(TOKEN, OTHER_TOKEN)
+    };
+
+    SessionBuilder::default()
+        .token(TOKEN, MockErc20::default())
+        .token(OTHER_TOKEN, MockErc20::default())
+        .pair(PAIR, pair, reserve_0, reserve_1)
+        .build()
+}
+
+// Sends `amount_in` of token0 to the pair and asks it for `amount_out` of token1.
+fn pair_swap(
+    fee: SwapFee,
+    reserves: (U256, U256),
+    amount_in: U256,
+    amount_out: U256,
+) -> Result<(), SimulationError> {
+    let mut session = session_with_pair(fee, reserves.0, reserves.1);
+    let token = Erc20::new(TOKEN);
+    token.set_balance(SENDER, amount_in, &mut session)?;
+    token.transfer(PAIR, amount_in, &mut session)?;
+
+    Pool::new(PAIR)
+        .swap(U256::ZERO, amount_out, SENDER, &mut session)
+        .map(|_| ())
+}
+
+// Amounts of up to 100 bits, spread over their bit length so small amounts are covered too.
+fn amount() -> impl Strategy<Value = U256> {
+    (1u32..=100, any::<u128>())
+        .prop_map(|(bits, value)| U256::from(value) % (U256::from(1) << bits) + U256::from(1))
+}
+
+fn fee() -> impl Strategy<Value = SwapFee> {
+    prop_oneof![Just(SwapFee::UNISWAP), Just(PANCAKESWAP)]
+}
+
+proptest! {
+    #[test]
+    fn amount_out_is_exactly_what_the_pair_accepts(
+        fee in fee(),
+        reserves in (amount(), amount()),
+        amount_in in amount(),
+    ) {
+        let amount_out = get_amount_out(amount_in, reserves.0, reserves.1, fee).unwrap();
+        prop_assume!(!amount_out.is_zero());
+
+        prop_assert!(pair_swap(fee, reserves, amount_in, amount_out).is_ok());
+        prop_assert!(pair_swap(fee, reserves, amount_in, amount_out + U256::from(1)).is_err());
+    }
+
+    #[test]
+    fn amount_in_is_enough_for_the_pair(
+        fee in fee(),
+        reserves in (amount(), amount()),
+        amount_out in amount(),
+    ) {
+        let amount_out = amount_out % reserves.1;
+        prop_assume!(!amount_out.is_zero());
+
+        let amount_in = get_amount_in(amount_out, reserves.0, reserves.1, fee).unwrap();
+
+        prop_assert!(pair_swap(fee, reserves, amount_in, amount_out).is_ok());
+        prop_assert!(get_amount_out(amount_in, reserves.0, reserves.1, fee).unwrap() >= amount_out);
+    }
+}
+
+#[test]
+fn invalid_inputs_are_errors() {
+    let fee = SwapFee::UNISWAP;
+    let reserve = U256::from(1_000_000);
+
+    for result in [
+        get_amount_out(U256::ZERO, reserve, reserve, fee),
+        get_amount_out(U256::from(1), U256::ZERO, reserve, fee),
+        get_amount_out(U256::MAX, reserve, reserve, fee),
+        get_amount_in(reserve, reserve, reserve, fee),
+        get_amount_in(U256::from(1), reserve, U256::ZERO, fee),
+        get_amount_in(U256::from(1), U256::MAX, reserve, fee),
+    ] {
+        assert!(matches!(
+            result,
+            Err(SimulationError::InvariantViolation(_))
+        ));
+    }
+
+    assert!(SwapFee::new(1000, 1000).is_err());
+    assert_eq!(SwapFee::new(3, 1000).unwrap(), SwapFee::UNISWAP);
+}